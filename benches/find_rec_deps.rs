@@ -0,0 +1,201 @@
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use ue_rec_deps_seeker::{find_rec_deps, options::AnalysisOptions, project::Project};
+
+struct SyntheticProject {
+    root_path: PathBuf,
+    entry_point: String,
+    output_file: String,
+}
+
+impl SyntheticProject {
+    // Generates `module_count` modules with `files_per_module` headers each, every header
+    // including `includes_per_file` pseudo-random others and every `cycle_every`th header
+    // including the entry point back to close a cycle
+    fn generate(
+        name: &str,
+        module_count: usize,
+        files_per_module: usize,
+        includes_per_file: usize,
+        cycle_every: usize,
+    ) -> Self {
+        let root_path = std::env::temp_dir().join(format!("ue_rec_deps_seeker_bench_{}", name));
+
+        if root_path.exists() {
+            fs::remove_dir_all(&root_path).unwrap();
+        }
+
+        let file_count = module_count * files_per_module;
+        let file_name = |index: usize| {
+            format!(
+                "Mod{}File{}.h",
+                index / files_per_module,
+                index % files_per_module
+            )
+        };
+
+        // Simple LCG so that every run generates the same include graph
+        let mut seed = 0x2545_f491_4f6c_dd1d_u64;
+        let mut next_random = move || {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) as usize
+        };
+
+        let mut include_folders = vec![];
+
+        for module in 0..module_count {
+            let public_folder = root_path
+                .join("Engine/Source/Runtime")
+                .join(format!("Mod{}", module))
+                .join("Public");
+            fs::create_dir_all(&public_folder).unwrap();
+
+            for index in module * files_per_module..(module + 1) * files_per_module {
+                let mut file = File::create(public_folder.join(file_name(index))).unwrap();
+
+                writeln!(file, "#pragma once").unwrap();
+
+                for _ in 0..includes_per_file {
+                    // Only include "later" files so that the only cycles are the injected ones
+                    let included = index + 1 + next_random() % file_count;
+                    if included < file_count {
+                        writeln!(file, "#include \"{}\"", file_name(included)).unwrap();
+                    }
+                }
+
+                if index != 0 && index % cycle_every == 0 {
+                    writeln!(file, "#include \"{}\"", file_name(0)).unwrap();
+                }
+            }
+
+            include_folders.push(public_folder);
+        }
+
+        Self::write_cmake_lists(&root_path, &include_folders);
+
+        Self {
+            entry_point: include_folders[0]
+                .join(file_name(0))
+                .to_string_lossy()
+                .to_string(),
+            output_file: root_path.join("rec_deps.txt").to_string_lossy().to_string(),
+            root_path,
+        }
+    }
+
+    // A single module where every header includes the next one and the last one includes the
+    // first, so the traversal goes `length` nodes deep
+    fn generate_chain(name: &str, length: usize) -> Self {
+        let root_path = std::env::temp_dir().join(format!("ue_rec_deps_seeker_bench_{}", name));
+
+        if root_path.exists() {
+            fs::remove_dir_all(&root_path).unwrap();
+        }
+
+        let public_folder = root_path.join("Engine/Source/Runtime/Chain/Public");
+        fs::create_dir_all(&public_folder).unwrap();
+
+        for index in 0..length {
+            let mut file = File::create(public_folder.join(format!("Chain{}.h", index))).unwrap();
+
+            writeln!(file, "#pragma once").unwrap();
+            writeln!(file, "#include \"Chain{}.h\"", (index + 1) % length).unwrap();
+        }
+
+        Self::write_cmake_lists(&root_path, std::slice::from_ref(&public_folder));
+
+        Self {
+            entry_point: public_folder.join("Chain0.h").to_string_lossy().to_string(),
+            output_file: root_path.join("rec_deps.txt").to_string_lossy().to_string(),
+            root_path,
+        }
+    }
+
+    fn write_cmake_lists(root_path: &Path, include_folders: &[PathBuf]) {
+        let includes_cmake = root_path.join("cmake-includes.cmake");
+        let mut includes_file = File::create(&includes_cmake).unwrap();
+        writeln!(includes_file, "set(INCLUDE_DIRECTORIES").unwrap();
+        for include_folder in include_folders.iter() {
+            writeln!(includes_file, "\t\"{}\"", include_folder.display()).unwrap();
+        }
+        writeln!(includes_file, ")").unwrap();
+
+        let mut cmake_lists = File::create(root_path.join("CMakeLists.txt")).unwrap();
+        writeln!(cmake_lists, "include(\"{}\")", includes_cmake.display()).unwrap();
+    }
+
+    fn root_path(&self) -> &str {
+        self.root_path.to_str().unwrap()
+    }
+}
+
+impl Drop for SyntheticProject {
+    fn drop(&mut self) {
+        if Path::new(&self.root_path).exists() {
+            fs::remove_dir_all(&self.root_path).unwrap();
+        }
+    }
+}
+
+fn bench_sizes(c: &mut Criterion) {
+    let mut group = c.benchmark_group("find_rec_deps");
+    group.sample_size(10);
+
+    for (name, module_count, files_per_module) in [("small", 4, 25), ("large", 32, 100)] {
+        let project = SyntheticProject::generate(name, module_count, files_per_module, 4, 10);
+
+        group.bench_with_input(
+            BenchmarkId::new("Project::create", name),
+            &project,
+            |b, project| b.iter(|| Project::create(project.root_path()).unwrap()),
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("end_to_end", name),
+            &project,
+            |b, project| {
+                b.iter(|| {
+                    find_rec_deps(AnalysisOptions::new(
+                        project.root_path(),
+                        &project.entry_point,
+                        &project.output_file,
+                    ))
+                    .unwrap()
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+fn bench_deep_chain(c: &mut Criterion) {
+    let mut group = c.benchmark_group("find_rec_deps");
+    group.sample_size(10);
+
+    let project = SyntheticProject::generate_chain("chain", 5000);
+
+    group.bench_function("end_to_end/chain_5000", |b| {
+        b.iter(|| {
+            find_rec_deps(AnalysisOptions::new(
+                project.root_path(),
+                &project.entry_point,
+                &project.output_file,
+            ))
+            .unwrap()
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_sizes, bench_deep_chain);
+criterion_main!(benches);
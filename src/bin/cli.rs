@@ -0,0 +1,424 @@
+use std::{io::stdout, process::exit};
+
+use anyhow::*;
+use clap::{Parser, Subcommand};
+use tracing_subscriber::{fmt::format::FmtSpan, EnvFilter};
+
+use ue_rec_deps_seeker::{
+    file_info::DEFAULT_OPEN_RETRIES,
+    find_rec_deps,
+    node::DEFAULT_MAX_CYCLES,
+    options::AnalysisOptions,
+    project::Project,
+    report,
+    report::{CycleOrder, ReportFormat, Severity},
+    run_manifest, self_test,
+};
+
+#[derive(Parser)]
+#[clap(about = "UE Recursive Dependencies Seeker without the GUI")]
+struct Cli {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+// `Analyze` holds every analysis flag, the command is only parsed once
+#[allow(clippy::large_enum_variant)]
+#[derive(Subcommand)]
+enum Command {
+    /// Find the recursive dependencies reachable from the entry point, exits with status 2 if
+    /// any was found
+    Analyze {
+        #[clap(short, long)]
+        project: String,
+        #[clap(
+            short,
+            long,
+            required_unless_present_any = &["changed-since", "entries-file"]
+        )]
+        entry: Option<String>,
+        /// File listing more entry points, one per line, blank lines and `#` comments are skipped
+        #[clap(long)]
+        entries_file: Option<String>,
+        /// Abort on an entry point that can't be opened instead of skipping it with a warning
+        #[clap(long)]
+        strict_entries: bool,
+        /// Give entry points under no include path a module of their own made from their
+        /// directory, e.g. for scratch headers
+        #[clap(long)]
+        ad_hoc_entries: bool,
+        /// A `.json` extension writes the report as JSON
+        #[clap(short, long)]
+        output: String,
+        /// Report format: text, json or mermaid, taken from the output file extension if not set
+        #[clap(long)]
+        format: Option<ReportFormat>,
+        /// Stop at the first cycle found
+        #[clap(long)]
+        fail_fast: bool,
+        /// Only report each cycle itself instead of every path from the entry point reaching it,
+        /// much smaller output on projects with many shared headers
+        #[clap(long)]
+        minimal_cycles: bool,
+        /// Keep running and analyze again whenever a file under the include paths changes
+        #[clap(long)]
+        watch: bool,
+        /// Append to the output file instead of overwriting it
+        #[clap(long)]
+        append: bool,
+        /// Treat the output as a directory and write a text file per file closing a loop
+        #[clap(long)]
+        split_by_endpoint: bool,
+        /// Also list the reachable files that aren't in any cycle
+        #[clap(long)]
+        list_clean_files: bool,
+        /// Also list the include line behind every edge of the cycles
+        #[clap(long)]
+        show_include_lines: bool,
+        /// Order of the cycles: length (shortest first), lexicographic or modules (spanning the
+        /// most modules first)
+        #[clap(long, default_value = "length")]
+        cycle_order: CycleOrder,
+        /// Only write the summary and metrics, not the cycles themselves
+        #[clap(long)]
+        stats_only: bool,
+        /// Leave out cycles of fewer files than this, 3 hides the mutual pairs
+        #[clap(long)]
+        min_cycle_length: Option<usize>,
+        /// Leave out cycles less severe than this: info (guarded headers only), warning (an
+        /// unguarded header) or error (through a source file)
+        #[clap(long)]
+        min_severity: Option<Severity>,
+        /// File of known cycles to leave out of the report, one per line as `A.h->B.h->C.h`
+        #[clap(long)]
+        suppressions: Option<String>,
+        /// Keep the cycles of a header including its own implementation file (`Foo.hpp` ->
+        /// `Foo_impl.cpp`), they're left out by default
+        #[clap(long)]
+        keep_template_impl_cycles: bool,
+        /// Fail if more distinct cycles than this are found
+        #[clap(long)]
+        max_allowed_cycles: Option<usize>,
+        /// Stop collecting recursive paths after this many, 0 collects all of them
+        #[clap(long, default_value_t = DEFAULT_MAX_CYCLES)]
+        max_cycles: usize,
+        /// Don't follow includes more than this many levels deep, cycles below that are missed
+        #[clap(long)]
+        max_depth: Option<usize>,
+        /// Treat `Foo.h`/`Foo.cpp` pairs as one unit
+        #[clap(long)]
+        pair_units: bool,
+        /// Also look for includes in the Public/Private/Classes/Internal subfolders of modules
+        #[clap(long)]
+        ue_subfolders: bool,
+        /// As a last resort look for unresolved includes anywhere under the project root (slow)
+        #[clap(long)]
+        search_whole_project: bool,
+        /// Check every module for each include and list the ones more than one module has a
+        /// file for (slow)
+        #[clap(long)]
+        diagnose_ambiguity: bool,
+        /// Compiler response file whose -I/-isystem paths are searched after all the modules
+        #[clap(long)]
+        response_file: Option<String>,
+        /// Times opening a file is tried again after a transient error, e.g. on network mounts
+        #[clap(long, default_value_t = DEFAULT_OPEN_RETRIES)]
+        open_retries: usize,
+        /// `EXTENSION=TYPE` (Header, Inline or Source) for project specific extensions, can be
+        /// repeated
+        #[clap(long = "extension")]
+        extensions: Vec<String>,
+        /// CMake file listing the modules, relative to the project root
+        #[clap(long)]
+        cmake_lists: Option<String>,
+        /// Folder the module paths of the cmake file start at, `*` matches any single folder, can
+        /// be repeated (`Engine/Plugins/*/Source`, `Engine/Source` and `Source` by default)
+        #[clap(long = "source-root")]
+        source_roots: Vec<String>,
+        /// Also start from every file changed since this git ref and only report cycles going
+        /// through one of the changed files
+        #[clap(long)]
+        changed_since: Option<String>,
+        /// Only expand a source file if it's the entry point, included sources are skipped
+        #[clap(long)]
+        root_sources_only: bool,
+        /// Only follow includes inside of this module, for whether it's circular in itself
+        #[clap(long)]
+        only_module: Option<String>,
+        /// File name pattern of precompiled header wrappers not to expand (`*` matches anything),
+        /// can be repeated
+        #[clap(long = "pch-pattern")]
+        pch_patterns: Vec<String>,
+        /// Don't follow includes into this module, can be repeated
+        #[clap(long = "exclude-module")]
+        excluded_modules: Vec<String>,
+        /// `ALIAS=MODULE`, lets the other options name the module by its alias, can be repeated
+        #[clap(long = "module-alias")]
+        module_aliases: Vec<String>,
+        /// Only follow `#include`s, not C++20 header unit imports
+        #[clap(long)]
+        skip_imports: bool,
+        /// Write the absolute paths of the files instead of just their names
+        #[clap(long)]
+        abs_paths: bool,
+    },
+    /// Find every cycle cluster (strongly connected component) of the whole project's include
+    /// graph, regardless of entry points. Exits with status 2 if any was found
+    Scc {
+        #[clap(short, long)]
+        project: String,
+        /// Only look at the files of this module
+        #[clap(short, long)]
+        module: Option<String>,
+        #[clap(short, long)]
+        output: String,
+    },
+    /// Print every include path from the entry point to the target file
+    Why {
+        #[clap(short, long)]
+        project: String,
+        #[clap(short, long)]
+        entry: String,
+        /// Absolute path or file name of the included file
+        target: String,
+    },
+    /// List the modules found in the CMake files with their include paths
+    Modules {
+        #[clap(short, long)]
+        project: String,
+        /// Write the list to this file instead of printing it
+        #[clap(short, long)]
+        output: Option<String>,
+    },
+    /// Run every `[[job]]` listed in a TOML manifest
+    Manifest { path: String },
+    /// Print the cycles added and removed between two JSON reports, fails if any were added
+    Diff { old: String, new: String },
+    /// Check that the tool works in this environment by analyzing a generated project with a
+    /// known cycle
+    Selftest,
+}
+
+fn main() -> Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .with_span_events(FmtSpan::CLOSE)
+        .init();
+
+    match Cli::parse().command {
+        Command::Analyze {
+            project,
+            entry,
+            entries_file,
+            strict_entries,
+            ad_hoc_entries,
+            output,
+            format,
+            fail_fast,
+            minimal_cycles,
+            watch,
+            append,
+            split_by_endpoint,
+            list_clean_files,
+            show_include_lines,
+            cycle_order,
+            stats_only,
+            min_cycle_length,
+            min_severity,
+            suppressions,
+            keep_template_impl_cycles,
+            max_allowed_cycles,
+            max_cycles,
+            max_depth,
+            pair_units,
+            ue_subfolders,
+            search_whole_project,
+            diagnose_ambiguity,
+            response_file,
+            open_retries,
+            extensions,
+            cmake_lists,
+            source_roots,
+            changed_since,
+            root_sources_only,
+            only_module,
+            pch_patterns,
+            excluded_modules,
+            module_aliases,
+            skip_imports,
+            abs_paths,
+        } => {
+            let mut options = AnalysisOptions {
+                project_path: project,
+                entry_points: entry.into_iter().collect(),
+                output_file_path: output,
+                ..Default::default()
+            }
+            .fail_fast(fail_fast)
+            .minimal_cycles(minimal_cycles)
+            .strict_entry_points(strict_entries)
+            .ad_hoc_entry_points(ad_hoc_entries)
+            .append(append)
+            .split_by_endpoint(split_by_endpoint)
+            .list_clean_files(list_clean_files)
+            .show_include_lines(show_include_lines)
+            .cycle_order(cycle_order)
+            .stats_only(stats_only)
+            .keep_template_impl_cycles(keep_template_impl_cycles)
+            .max_cycles((max_cycles > 0).then_some(max_cycles))
+            .max_depth(max_depth)
+            .pair_units(pair_units)
+            .ue_subfolders(ue_subfolders)
+            .open_retries(open_retries)
+            .search_whole_project(search_whole_project)
+            .diagnose_ambiguity(diagnose_ambiguity)
+            .root_sources_only(root_sources_only)
+            .excluded_modules(excluded_modules.into_iter().collect())
+            .skip_imports(skip_imports)
+            .abs_paths(abs_paths);
+            for pch_pattern in pch_patterns {
+                options = options.pch_pattern(&pch_pattern);
+            }
+            for source_root in source_roots {
+                options = options.source_root(&source_root);
+            }
+            for module_alias in module_aliases {
+                match module_alias.split_once('=') {
+                    Some((alias, module)) => options = options.module_alias(alias, module),
+                    None => bail!("Module alias '{}' isn't ALIAS=MODULE", module_alias),
+                }
+            }
+            for extension in extensions {
+                match extension.split_once('=') {
+                    Some((extension, file_type)) => {
+                        options = options.extension(extension, file_type.parse()?)
+                    }
+                    None => bail!("Extension mapping '{}' isn't EXTENSION=TYPE", extension),
+                }
+            }
+            if let Some(only_module) = only_module {
+                options = options.only_module(&only_module);
+            }
+            if let Some(format) = format {
+                options = options.format(format);
+            }
+            if let Some(cmake_lists) = cmake_lists {
+                options = options.cmake_lists(&cmake_lists);
+            }
+            if let Some(entries_file) = entries_file {
+                options = options.entry_points_file(&entries_file);
+            }
+            if let Some(min_cycle_length) = min_cycle_length {
+                options = options.min_cycle_length(min_cycle_length);
+            }
+            if let Some(min_severity) = min_severity {
+                options = options.min_severity(min_severity);
+            }
+            if let Some(suppressions) = suppressions {
+                options = options.suppressions_file(&suppressions);
+            }
+            if let Some(max_allowed_cycles) = max_allowed_cycles {
+                options = options.max_allowed_cycles(max_allowed_cycles);
+            }
+            if let Some(response_file) = response_file {
+                options = options.response_file(&response_file);
+            }
+            if let Some(changed_since) = changed_since {
+                options = options.changed_since(&changed_since);
+            }
+
+            if watch {
+                ue_rec_deps_seeker::watch(options, |cycle_report, last_report| {
+                    let last_report = match last_report {
+                        Some(last_report) => last_report,
+                        None => {
+                            println!("{} distinct cycle(s)", cycle_report.len());
+                            return;
+                        }
+                    };
+
+                    let diff = report::diff(
+                        last_report.recursive_paths(),
+                        cycle_report.recursive_paths(),
+                    );
+
+                    for cycle in diff.removed.iter() {
+                        println!("- {}", report::format_cycle(cycle));
+                    }
+
+                    for cycle in diff.added.iter() {
+                        println!("+ {}", report::format_cycle(cycle));
+                    }
+
+                    println!(
+                        "{} distinct cycle(s) ({:+})",
+                        cycle_report.len(),
+                        cycle_report.len() as isize - last_report.len() as isize
+                    );
+                })?;
+            } else if !find_rec_deps(options)?.is_empty() {
+                exit(2);
+            }
+        }
+        Command::Scc {
+            project,
+            module,
+            output,
+        } => {
+            if !find_rec_deps(AnalysisOptions::scc(&project, module.as_deref(), &output))?
+                .is_empty()
+            {
+                exit(2);
+            }
+        }
+        Command::Why {
+            project,
+            entry,
+            target,
+        } => {
+            for path in Project::create(&project)?.paths_to(&entry, &target)? {
+                println!("{}", path.join("->"));
+            }
+        }
+        Command::Modules { project, output } => {
+            let project = Project::create(&project)?;
+
+            match output {
+                Some(output) => project.dump_modules(&output)?,
+                None => project.write_modules(&mut stdout())?,
+            }
+        }
+        Command::Manifest { path } => run_manifest(&path)?,
+        Command::Selftest => {
+            if self_test()? {
+                println!("PASS");
+            } else {
+                println!("FAIL");
+                exit(1);
+            }
+        }
+        Command::Diff { old, new } => {
+            let diff = report::diff(&report::read_json(&old)?, &report::read_json(&new)?);
+
+            for cycle in diff.removed.iter() {
+                println!("- {}", report::format_cycle(cycle));
+            }
+
+            for cycle in diff.added.iter() {
+                println!("+ {}", report::format_cycle(cycle));
+            }
+
+            println!(
+                "{} cycle(s) added, {} cycle(s) removed",
+                diff.added.len(),
+                diff.removed.len()
+            );
+
+            if !diff.added.is_empty() {
+                exit(1);
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -1,23 +1,61 @@
 #[macro_use]
-extern crate log;
+extern crate tracing;
 
 pub mod file_info {
     use std::{
         cell::RefCell,
+        collections::{BTreeMap, BTreeSet, HashMap, HashSet},
         fmt::{Debug, Display, Formatter},
         fs::File,
-        io::{BufRead, BufReader},
+        io::{self, BufRead, BufReader, ErrorKind},
         path::Path,
         rc::Rc,
+        str::FromStr,
+        thread,
+        time::Duration,
     };
 
     use anyhow::*;
+    use serde::Deserialize;
 
-    #[derive(Eq, PartialEq, Hash)]
+    // Ordered the way reports group files: headers, then what's included like them, then the
+    // translation units
+    #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Deserialize)]
     pub enum FileType {
         Header,
-        Source,
         Inline,
+        Source,
+        // Only for leaf files, see `FileInfo::leaf`
+        Unsupported,
+    }
+
+    impl FileType {
+        // Case insensitive, `.H` and `.CPP` show up on case insensitive file systems
+        pub fn from_extension(extension: &str) -> Option<Self> {
+            match extension.to_lowercase().as_str() {
+                "h" | "hh" | "hpp" | "hxx" => Some(FileType::Header),
+                "c" | "cc" | "cpp" | "cxx" | "c++" => Some(FileType::Source),
+                "inl" | "ipp" => Some(FileType::Inline),
+                _ => None,
+            }
+        }
+    }
+
+    // By the variant name, case insensitive, e.g. for `hin=Header` on the command line
+    impl FromStr for FileType {
+        type Err = Error;
+
+        fn from_str(name: &str) -> Result<Self> {
+            match name.to_lowercase().as_str() {
+                "header" => Ok(FileType::Header),
+                "inline" => Ok(FileType::Inline),
+                "source" => Ok(FileType::Source),
+                _ => bail!(
+                    "Unknown file type '{}', expected Header, Inline or Source",
+                    name
+                ),
+            }
+        }
     }
 
     impl Display for FileType {
@@ -26,541 +64,6962 @@ pub mod file_info {
                 FileType::Header => write!(f, "Header"),
                 FileType::Source => write!(f, "Source"),
                 FileType::Inline => write!(f, "Inline"),
+                FileType::Unsupported => write!(f, "Unsupported"),
             }
         }
     }
 
     #[derive(Eq, PartialEq, Hash)]
     pub struct FileInfo {
-        pub abs_path: String,
-        pub file_name: String,
-        pub module: String,
+        // Interned by the project's `PathPool`
+        pub abs_path: Rc<str>,
+        pub file_name: Rc<str>,
+        pub module: Rc<str>,
         pub file_type: FileType,
-        pub includes: Vec<String>,
+        pub includes: Vec<Include>,
+        pub dynamic_includes: Vec<String>,
+        // `.generated.`/`.gen.` includes left out by the scanner, a cycle closing only through
+        // one of them can't be found
+        pub skipped_generated_includes: usize,
+        // `#pragma once` or an `#ifndef`/`#define` pair as the first directives
+        pub guarded: bool,
         pub processed: bool,
+        // Got a node during traversal, so it's reachable from an entry point
+        pub reached: bool,
+        // Fully explored without finding any cycle below it, so it never needs a node again
+        pub cycle_free: bool,
+        // Why the file couldn't be parsed, such a file is kept as a leaf without includes
+        pub leaf_reason: Option<String>,
+        // Resolved to outside of the project root, likely missing on other machines
+        pub external: bool,
+        // Distinct include paths that did/didn't resolve to a file during traversal, many
+        // unresolved ones point to misconfigured include paths
+        pub resolved_includes: BTreeSet<String>,
+        pub unresolved_includes: BTreeSet<String>,
+        // The include each resolved file was reached through, by the file's absolute path, the
+        // first one if it's included more than once
+        pub include_edges: BTreeMap<Rc<str>, Include>,
     }
 
-    impl FileInfo {
-        pub fn create(
-            abs_path: &str,
-            modules: &[(String, Vec<String>)],
-        ) -> Result<Rc<RefCell<FileInfo>>> {
-            let file = File::open(Path::new(abs_path))?;
-
-            let file_name = abs_path.split('/').last().unwrap();
-            let file_type_str = file_name.split('.').last().unwrap();
+    #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Deserialize)]
+    pub enum IncludeKind {
+        // `#include "Foo.h"`
+        Include,
+        // C++20 header unit, `import "Foo.h";`
+        Import,
+    }
 
-            let file_type = match file_type_str {
-                "h" | "hpp" => FileType::Header,
-                "c" | "cpp" => FileType::Source,
-                "inl" => FileType::Inline,
-                _ => bail!(
-                    "{}",
-                    format!("File type is not supported: '{}'", file_type_str)
-                ),
-            };
+    #[derive(Clone, Debug, Eq, PartialEq, Hash)]
+    pub struct Include {
+        pub kind: IncludeKind,
+        // Path as it's written between the quotes/angle brackets
+        pub path: String,
+        // The whole `#include` line as it's written in the file
+        pub line: String,
+        pub line_number: usize,
+    }
 
-            let file_lines = BufReader::new(file).lines();
+    // What `FileInfo::split_comments` carries over from one line to the next
+    #[derive(Default)]
+    struct LineState {
+        raw_string_delimiter: Option<String>,
+        in_block_comment: bool,
+    }
 
-            let mut includes = vec![];
+    // What `FileInfo::scan` found on the lines of a file
+    #[derive(Clone, Debug, Default)]
+    pub struct ScannedIncludes {
+        pub includes: Vec<Include>,
+        pub dynamic_includes: Vec<String>,
+        pub skipped_generated_includes: usize,
+        pub guarded: bool,
+    }
 
-            for mut line in file_lines.flatten() {
-                if line.contains("#include") {
-                    if line.contains(".generated.") || line.contains(".gen.") {
-                        continue;
-                    }
+    // An include line with this in a comment is dropped, for includes known to be safe
+    pub const IGNORE_MARKER: &str = "uecds:ignore";
 
-                    line = line.trim().to_owned();
+    pub const DEFAULT_OPEN_RETRIES: usize = 2;
 
-                    let l_split = line.split(' ');
+    #[derive(Clone, Debug)]
+    pub struct ScanOptions {
+        // Stop looking for includes once this many lines of actual code (not includes, comments
+        // or other preprocessor directives) followed the last include. Off by default since a
+        // late include would be missed
+        pub include_cutoff: Option<usize>,
+        // Times opening a file is tried again after a transient error, see `open_with_retries`
+        pub open_retries: usize,
+        // Project specific extensions (without the dot), checked before the built-in ones
+        pub extensions: HashMap<String, FileType>,
+    }
 
-                    includes.push(l_split.last().unwrap().replace('\"', "").to_owned());
-                }
+    impl Default for ScanOptions {
+        fn default() -> Self {
+            Self {
+                include_cutoff: None,
+                open_retries: DEFAULT_OPEN_RETRIES,
+                extensions: HashMap::new(),
             }
-
-            let module = modules
-                .iter()
-                .rfind(|(modl, _include_paths)| abs_path.contains(modl.as_str()));
-
-            let module = match module {
-                Some(module) => module.0.clone(),
-                None => bail!("Couldn't find the module of the file: {}", abs_path),
-            };
-
-            Ok(Rc::new(RefCell::new(Self {
-                abs_path: abs_path.to_string(),
-                file_name: file_name.to_owned(),
-                module,
-                file_type,
-                includes,
-                processed: false,
-            })))
         }
     }
 
-    impl Debug for FileInfo {
-        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-            writeln!(f, "FileInfo(")?;
-            writeln!(f, "\tAbsolute Path: {}", self.abs_path)?;
-            writeln!(f, "\tFile Name: {}", self.file_type)?;
-            writeln!(f, "\tModule: {}", self.module)?;
-            writeln!(f, "\tFile Type: {}", self.file_type)?;
-            writeln!(f, "\tIncludes: {:?}", self.includes)?;
-            writeln!(f, "\tProcessed: {}", self.processed)?;
-            writeln!(f, ")")
+    impl ScanOptions {
+        pub fn file_type(&self, extension: &str) -> Option<FileType> {
+            self.extensions
+                .iter()
+                .find(|(custom_extension, _file_type)| {
+                    custom_extension.eq_ignore_ascii_case(extension)
+                })
+                .map(|(_custom_extension, file_type)| *file_type)
+                .or_else(|| FileType::from_extension(extension))
         }
     }
-}
 
-pub mod node {
-    use std::{
-        cell::RefCell,
-        collections::{HashMap, HashSet},
-        fmt::{Debug, Formatter},
-        rc::Rc,
-    };
+    // Windows paths (`C:\\Project\\Foo.h`, e.g. from the file dialog or a response file) can show
+    // up on any platform, so both separators are handled no matter the OS
+    pub fn file_name_of(path: &str) -> &str {
+        path.rsplit(['/', '\\']).next().unwrap_or(path)
+    }
 
-    use itertools::Itertools;
+    // Module names and include paths from the cmake files use `/`, paths are compared in that
+    // form
+    pub fn normalize_separators(path: &str) -> String {
+        path.replace('\\', "/")
+    }
 
-    use crate::{file_info::FileInfo, project::Project};
+    // The include paths of a single file as they're written, without modules or a `Project`,
+    // for scripts that only want to know what a file includes
+    pub fn parse_includes(abs_path: &str) -> Result<Vec<String>> {
+        let scan_options = ScanOptions::default();
+        let file = open_with_retries(Path::new(abs_path), scan_options.open_retries)?;
 
-    #[derive(Eq)]
-    pub struct Node {
-        file_info: Rc<RefCell<FileInfo>>,
-        prev: Option<Rc<RefCell<Node>>>,
-        children: Vec<Rc<RefCell<Node>>>,
-        node_path: Vec<Rc<RefCell<FileInfo>>>,
+        Ok(
+            FileInfo::scan(abs_path, file, &scan_options, &DefaultIncludeScanner)?
+                .includes
+                .into_iter()
+                .map(|include| include.path)
+                .collect(),
+        )
     }
 
-    impl Node {
-        pub fn create(
-            file_info: &Rc<RefCell<FileInfo>>,
-            prev: Option<Rc<RefCell<Node>>>,
-        ) -> Rc<RefCell<Self>> {
-            let mut node_path = vec![];
+    // Network mounted trees (NFS/SMB) fail to open a file every now and then. Missing files and
+    // denied permissions won't fix themselves, anything else is tried again with a growing delay
+    pub fn open_with_retries(path: &Path, retries: usize) -> io::Result<File> {
+        let mut attempt = 0;
 
-            if let Some(previous) = prev.clone() {
-                node_path.extend((*previous).borrow().node_path.clone());
-            }
-            node_path.push(file_info.clone());
+        loop {
+            match File::open(path) {
+                std::result::Result::Ok(file) => return std::result::Result::Ok(file),
+                Err(error)
+                    if attempt < retries
+                        && !matches!(
+                            error.kind(),
+                            ErrorKind::NotFound | ErrorKind::PermissionDenied
+                        ) =>
+                {
+                    attempt += 1;
 
-            Rc::new(RefCell::new(Self {
-                file_info: file_info.clone(),
-                prev,
-                children: vec![],
-                node_path,
-            }))
+                    warn!(
+                        "Couldn't open {} ({}), retrying ({}/{})",
+                        path.display(),
+                        error,
+                        attempt,
+                        retries
+                    );
+                    thread::sleep(Duration::from_millis(50 << attempt));
+                }
+                Err(error) => return Err(error),
+            }
         }
+    }
 
-        pub fn traverse(
-            starting_node: &Rc<RefCell<Node>>,
-            project: &mut Project,
-        ) -> HashMap<String, HashSet<Vec<String>>> {
-            let mut recursive_paths: HashMap<String, HashSet<Vec<String>>> = HashMap::new();
-
-            let mut current = starting_node.clone();
+    // Hands out a single shared allocation per distinct string, so the same path is never stored
+    // twice and two interned paths are equal exactly when they point to the same allocation
+    #[derive(Default)]
+    pub struct PathPool {
+        strings: HashSet<Rc<str>>,
+    }
 
-            loop {
-                let current_processed = (*(*current).borrow().file_info).borrow().processed;
+    impl PathPool {
+        pub fn intern(&mut self, string: &str) -> Rc<str> {
+            if let Some(interned) = self.strings.get(string) {
+                return interned.clone();
+            }
 
-                // If the current node is already processed
-                if current_processed {
-                    let current_prev = (*current).borrow().prev.clone();
+            let interned: Rc<str> = Rc::from(string);
+            self.strings.insert(interned.clone());
 
-                    // Go Back
-                    if let Some(previous) = current_prev {
-                        current = previous;
-                        continue;
-                    } else {
-                        break;
-                    }
-                }
+            interned
+        }
 
-                // If it's not yet fully processed
-                // Check if the node has children
-                let mut current_children = (*current).borrow().children.clone();
-                if current_children.is_empty() {
-                    // If the doesn't have children yet
-                    // Check if the file o the node actually has any includes
-                    let current_file_info = (*current).borrow().file_info.clone();
-                    if !(*current_file_info).borrow().includes.is_empty() {
-                        // If there are any includes, create node children
-                        Self::create_node_children(current.clone(), project);
-                    } else {
-                        // If there was non in the first place, we can count this node as a processed
-                        // one and skip loop iteration
-                        (*(*current).borrow_mut().file_info).borrow_mut().processed = true;
-                        continue;
-                    }
-                }
+        pub fn len(&self) -> usize {
+            self.strings.len()
+        }
 
-                // If the node has children, lets fine an unprocessed one
-                current_children = (*current).borrow().children.clone();
-                if let Some(unprocessed_child) = current_children
-                    .iter()
-                    .find(|&child| !(*(*child.clone()).borrow().file_info).borrow().processed)
-                {
-                    // If we find one, we check if it's not a recursive one
-                    let (is_unprocessed_child_recursive, file_name) =
-                        (*unprocessed_child.clone()).borrow().is_recursive();
-                    if is_unprocessed_child_recursive {
-                        // If it is recursive, it can be considered processed right away and we print
-                        // out its path
-                        (*(*unprocessed_child.clone()).borrow_mut().file_info)
-                            .borrow_mut()
-                            .processed = true;
+        pub fn is_empty(&self) -> bool {
+            self.strings.is_empty()
+        }
+    }
 
-                        let readable_path = (*unprocessed_child.clone()).borrow().readable_path();
+    pub enum IncludeArgument {
+        // "Foo.h" or <Foo.h>
+        Path(String),
+        // Anything else, usually a macro that expands to the actual path
+        Dynamic(String),
+        // import "Foo.h"; or import <Foo>;
+        Import(String),
+    }
 
-                        let key = file_name.unwrap();
+    impl IncludeArgument {
+        pub fn parse(line: &str) -> Option<Self> {
+            if let Some(argument) = Self::import_argument(line) {
+                // Named modules (`import Foo;`) aren't files, so there's nothing to resolve
+                let closing = match argument.chars().next() {
+                    Some('"') => '"',
+                    Some('<') => '>',
+                    _ => return None,
+                };
 
-                        if let Some(path) = recursive_paths.get_mut(key.as_str()) {
-                            path.insert(readable_path.clone());
-                        } else {
-                            let mut set = HashSet::new();
-                            set.insert(readable_path.clone());
+                return argument[1..]
+                    .split_once(closing)
+                    .map(|(path, _rest)| IncludeArgument::Import(path.to_owned()));
+            }
 
-                            recursive_paths.insert(key, set);
-                        }
+            let (_, argument) = line.split_once("#include")?;
+            let argument = argument.trim();
 
-                        info!("RECURSIVE PATH FOUND: {:?}", readable_path);
-                    } else {
-                        // If it isn't, we can go deeper into the tree
-                        current = unprocessed_child.clone();
-                    }
-                } else {
-                    // If there's none left, we can call this node processed and skip the loop iteration
-                    (*(*current).borrow_mut().file_info).borrow_mut().processed = true;
-                }
+            if argument.is_empty() {
+                return None;
             }
 
-            recursive_paths
+            let closing = match argument.chars().next() {
+                Some('"') => '"',
+                Some('<') => '>',
+                _ => return Some(IncludeArgument::Dynamic(argument.to_owned())),
+            };
+
+            match argument[1..].split_once(closing) {
+                Some((path, _rest)) => Some(IncludeArgument::Path(path.to_owned())),
+                None => Some(IncludeArgument::Dynamic(argument.to_owned())),
+            }
         }
 
-        fn create_node_children(node: Rc<RefCell<Node>>, project: &mut Project) {
-            let file_info = node.borrow().file_info.clone();
+        // What follows the `import` keyword, `export import` included
+        pub fn import_argument(line: &str) -> Option<&str> {
+            let line = line.trim_start();
+            let line = line
+                .strip_prefix("export")
+                .filter(|rest| rest.starts_with(char::is_whitespace))
+                .map_or(line, |rest| rest.trim_start());
 
-            let node_children = (*file_info)
-                .borrow()
-                .includes
-                .iter()
-                .filter_map(|include| {
-                    match project.get_file(include, &(*file_info).borrow().module) {
-                        Ok(include_file_info) => {
-                            Some(Node::create(&include_file_info, Some(node.clone())))
-                        }
-                        Err(_) => None,
-                    }
+            line.strip_prefix("import")
+                .filter(|rest| {
+                    rest.starts_with(|c: char| c.is_whitespace() || c == '"' || c == '<')
                 })
-                .collect();
+                .map(|rest| rest.trim())
+        }
+    }
 
-            node.borrow_mut().children = node_children;
+    // Finds the includes in the (backslash continuation joined) lines of a file, for include
+    // macros or conventions the default scanner doesn't know about
+    pub trait IncludeScanner {
+        // The include on the line, `None` if it has none or it should be ignored
+        fn scan_line(&self, line: &str) -> Option<IncludeArgument>;
+
+        // Whether the line counts as an include for `ScanOptions::include_cutoff`
+        fn is_include_line(&self, line: &str) -> bool {
+            line.contains("#include") || IncludeArgument::import_argument(line).is_some()
         }
+    }
 
-        fn is_recursive(&self) -> (bool, Option<String>) {
-            let mut abs_paths = self
-                .node_path
-                .iter()
-                .map(|file_info| (*file_info).borrow().abs_path.clone());
+    // Plain `#include` lines and header unit imports, skipping the UHT generated headers
+    #[derive(Copy, Clone, Debug, Default)]
+    pub struct DefaultIncludeScanner;
 
-            if !abs_paths.all_unique() {
-                (
-                    true,
-                    Some((*self.node_path.last().unwrap()).borrow().file_name.clone()),
-                )
-            } else {
-                (false, None)
+    impl IncludeScanner for DefaultIncludeScanner {
+        fn scan_line(&self, line: &str) -> Option<IncludeArgument> {
+            if line.contains(".generated.") || line.contains(".gen.") {
+                return None;
             }
-        }
 
-        fn readable_path(&self) -> Vec<String> {
-            self.node_path
-                .iter()
-                .map(|node| (*node).borrow().file_name.clone())
-                .collect()
+            IncludeArgument::parse(line)
         }
     }
 
-    impl PartialEq for Node {
-        fn eq(&self, other: &Self) -> bool {
-            self.file_info == other.file_info && self.prev == other.prev
-        }
-    }
+    impl FileInfo {
+        #[instrument(skip(modules, scan_options, include_scanner, path_pool))]
+        pub fn create(
+            abs_path: &str,
+            modules: &[(String, Vec<String>)],
+            scan_options: &ScanOptions,
+            include_scanner: &dyn IncludeScanner,
+            path_pool: &mut PathPool,
+        ) -> Result<Rc<RefCell<FileInfo>>> {
+            let file = open_with_retries(Path::new(abs_path), scan_options.open_retries)?;
 
-    impl Debug for Node {
-        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-            writeln!(f, "Node (")?;
-            writeln!(f, "\tFile Info: {}", (*self.file_info).borrow().abs_path)?;
-            writeln!(
-                f,
-                "\tPrevious Node: {}",
-                match self.prev.clone() {
-                    Some(previous_node) => (*(*previous_node).borrow().file_info)
-                        .borrow()
-                        .file_name
-                        .clone(),
-                    None => "None".to_owned(),
-                }
-            )?;
-            writeln!(
-                f,
-                "\tChildren: {:?}",
-                self.children
-                    .iter()
-                    .map(|child| { (*(**child).borrow().file_info).borrow().file_name.clone() })
-                    .collect::<Vec<String>>()
-            )?;
-            writeln!(f, "\tNode Path: {:?}", self.node_path)?;
-            writeln!(f, ")")
-        }
-    }
-}
+            let file_name = file_name_of(abs_path);
+            let file_type_str = file_name.split('.').next_back().unwrap();
 
-pub mod project {
-    use std::{
-        cell::RefCell,
-        collections::{HashMap, HashSet},
-        fmt::{Debug, Formatter},
-        fs::File,
-        io::{BufRead, BufReader},
-        iter::FromIterator,
-        path::Path,
-        rc::Rc,
-    };
+            let file_type = match scan_options.file_type(file_type_str) {
+                Some(file_type) => file_type,
+                None => bail!(
+                    "File type is not supported: '{}' ({})",
+                    file_type_str,
+                    abs_path
+                ),
+            };
 
-    use anyhow::*;
+            let ScannedIncludes {
+                includes,
+                dynamic_includes,
+                skipped_generated_includes,
+                guarded,
+            } = Self::scan(abs_path, file, scan_options, include_scanner)?;
 
-    use crate::file_info::FileInfo;
+            let module = match Self::find_module(abs_path, modules) {
+                Some(module) => module,
+                None => bail!("Couldn't find the module of the file: {}", abs_path),
+            };
 
-    pub struct Project {
-        pub root_path: String,
-        pub modules: Vec<(String, Vec<String>)>,
-        pub files: Vec<Rc<RefCell<FileInfo>>>,
-        pub circular_dependency_paths: HashSet<Vec<String>>,
-    }
+            Ok(Rc::new(RefCell::new(Self {
+                abs_path: path_pool.intern(abs_path),
+                file_name: path_pool.intern(file_name),
+                module: path_pool.intern(&module),
+                file_type,
+                includes,
+                dynamic_includes,
+                skipped_generated_includes,
+                guarded,
+                processed: false,
+                reached: false,
+                cycle_free: false,
+                leaf_reason: None,
+                external: false,
+                resolved_includes: BTreeSet::new(),
+                unresolved_includes: BTreeSet::new(),
+                include_edges: BTreeMap::new(),
+            })))
+        }
 
-    impl Project {
-        pub fn create(project_path: &str) -> Result<Self> {
-            let cmake_lists_file = File::open(Path::new(
-                (project_path.to_string() + "/CMakeLists.txt").as_str(),
-            ))?;
+        // The includes on the lines of an opened file, the part of `create` that doesn't need
+        // modules
+        pub fn scan(
+            abs_path: &str,
+            file: File,
+            scan_options: &ScanOptions,
+            include_scanner: &dyn IncludeScanner,
+        ) -> Result<ScannedIncludes> {
+            let file_lines = BufReader::new(file).lines();
 
-            let mut modules: HashMap<String, HashSet<String>> = HashMap::new();
+            let mut includes = vec![];
+            let mut dynamic_includes = vec![];
+            let mut skipped_generated_includes = 0;
+            let mut code_lines_since_include = None;
+            // The first line's index, the code and the comments of the lines joined so far
+            let mut continued_line: Option<(usize, String, String)> = None;
+            let mut line_state = LineState::default();
+            let mut first_directives = vec![];
 
-            let cmake_lists_lines = BufReader::new(cmake_lists_file).lines();
+            for (line_ind, line) in file_lines.enumerate() {
+                // Reading can fail midway (e.g. on a flaky network mount), carrying on would
+                // silently drop the rest of the includes
+                let line = line.with_context(|| {
+                    format!("Couldn't read line {} of {}", line_ind + 1, abs_path)
+                })?;
 
-            for cmake_lists_line in cmake_lists_lines.flatten() {
-                let stripped_cll = cmake_lists_line.replace(' ', "");
+                // `// #include "Old.h"`, includes inside `/* */` blocks and raw string literals
+                // (e.g. embedded shader or source text) aren't includes
+                let (line, comments) = Self::split_comments(&line, &mut line_state);
 
-                if stripped_cll.contains("include(") {
-                    let include = stripped_cll.replace("include(\"", "").replace("\")", "");
+                // A trailing backslash joins the next line onto this one (without a space, like
+                // the preprocessor does), the logical line keeps the number of its first line
+                if let Some(continued) = line.trim_end().strip_suffix('\\') {
+                    let (_first_line_ind, joined, joined_comments) = continued_line
+                        .get_or_insert_with(|| (line_ind, String::new(), String::new()));
+                    joined.push_str(continued);
+                    joined_comments.push_str(&comments);
+                    continue;
+                }
 
-                    if !include.contains("includes") {
-                        continue;
+                let (line_ind, line, comments) = match continued_line.take() {
+                    Some((first_line_ind, mut joined, mut joined_comments)) => {
+                        joined.push_str(&line);
+                        joined_comments.push_str(&comments);
+                        (first_line_ind, joined, joined_comments)
                     }
+                    None => (line_ind, line, comments),
+                };
 
-                    let include_cmake_file = File::open(Path::new(include.clone().as_str()))?;
-
-                    let include_cmake_file_lines = BufReader::new(include_cmake_file).lines();
+                if include_scanner.is_include_line(&comments) {
+                    debug!(
+                        "{}:{}: skipped '{}' (comment)",
+                        abs_path,
+                        line_ind + 1,
+                        comments.trim()
+                    );
+                }
 
-                    for include_cmake_file_line in include_cmake_file_lines.flatten() {
-                        let stripped_ifl = include_cmake_file_line.replace(' ', "");
+                // Only a marker in a comment counts, not one in e.g. a string literal
+                let has_ignore_marker = comments.contains(IGNORE_MARKER);
 
-                        if stripped_ifl.contains('\"') {
-                            let inc_folder = stripped_ifl
-                                .replace('\"', "")
-                                .replace('\t', "")
-                                .replace('\n', "");
+                if first_directives.len() < 2 {
+                    if let Some(directive) = line.trim().strip_prefix('#') {
+                        first_directives.push(
+                            directive
+                                .split_whitespace()
+                                .map(str::to_owned)
+                                .collect::<Vec<_>>(),
+                        );
+                    }
+                }
 
-                            if inc_folder.contains("Intermediate") {
-                                continue;
+                if let Some(include_cutoff) = scan_options.include_cutoff {
+                    if !include_scanner.is_include_line(&line) {
+                        // Counting only starts with the first include, so license headers or
+                        // anything else before the includes never cut the scan short
+                        if let Some(code_lines) = code_lines_since_include.as_mut() {
+                            if Self::is_code_line(&line) {
+                                *code_lines += 1;
                             }
 
-                            let start_ind = match inc_folder.rfind("Engine/") {
-                                Some(start_ind) => start_ind,
-                                None => bail!("Couldn't get start_ind"),
-                            };
-
-                            let module = inc_folder[start_ind..]
-                                .replace("/Public", "")
-                                .replace("/Private", "");
-
-                            if modules.contains_key(module.clone().as_str()) {
-                                modules
-                                    .get_mut(module.clone().as_str())
-                                    .unwrap()
-                                    .insert(inc_folder);
-                            } else {
-                                modules.insert(module.clone(), HashSet::from_iter([inc_folder]));
+                            if *code_lines >= include_cutoff {
+                                break;
                             }
                         }
+                    } else {
+                        code_lines_since_include = Some(0);
                     }
                 }
-            }
 
-            let mut res_modules: Vec<(String, Vec<String>)> = modules
-                .iter()
-                .map(|(module, include_paths)| {
-                    (
-                        module.clone(),
-                        include_paths.iter().cloned().collect::<Vec<String>>(),
-                    )
-                })
-                .collect();
-            res_modules.sort_by(|(mod1, _inc1), (mod2, _inc2)| Ord::cmp(&mod1.len(), &mod2.len()));
+                // The scanner's decisions, for when the includes of a file look wrong
+                let is_include_line = include_scanner.is_include_line(&line);
 
-            Ok(Self {
-                root_path: project_path.to_string(),
-                modules: res_modules,
-                files: vec![],
-                circular_dependency_paths: HashSet::new(),
-            })
-        }
+                if has_ignore_marker {
+                    if is_include_line {
+                        debug!(
+                            "{}:{}: skipped '{}' (ignore marker)",
+                            abs_path,
+                            line_ind + 1,
+                            line.trim()
+                        );
+                    }
+                    continue;
+                }
 
-        pub fn create_file_info(&mut self, abs_path: &str) -> Result<Rc<RefCell<FileInfo>>> {
-            let file_info = FileInfo::create(abs_path, &self.modules)?;
+                match include_scanner.scan_line(&line) {
+                    Some(IncludeArgument::Path(path)) => {
+                        debug!("{}:{}: kept '{}'", abs_path, line_ind + 1, line.trim());
+                        includes.push(Include {
+                            kind: IncludeKind::Include,
+                            path,
+                            line: line.trim().to_owned(),
+                            line_number: line_ind + 1,
+                        })
+                    }
+                    Some(IncludeArgument::Import(path)) => {
+                        debug!(
+                            "{}:{}: kept '{}' (import)",
+                            abs_path,
+                            line_ind + 1,
+                            line.trim()
+                        );
+                        includes.push(Include {
+                            kind: IncludeKind::Import,
+                            path,
+                            line: line.trim().to_owned(),
+                            line_number: line_ind + 1,
+                        })
+                    }
+                    Some(IncludeArgument::Dynamic(argument)) => {
+                        debug!(
+                            "{}:{}: skipped '{}' (macro)",
+                            abs_path,
+                            line_ind + 1,
+                            line.trim()
+                        );
+                        warn!(
+                            "Dynamic include '{}' in {} can't be resolved, skipping it",
+                            argument, abs_path
+                        );
+                        dynamic_includes.push(argument)
+                    }
+                    None if is_include_line => {
+                        let reason = if line.contains(".generated.") || line.contains(".gen.") {
+                            skipped_generated_includes += 1;
+                            "generated"
+                        } else {
+                            "rejected by the include scanner"
+                        };
 
-            self.files.push(file_info.clone());
+                        debug!(
+                            "{}:{}: skipped '{}' ({})",
+                            abs_path,
+                            line_ind + 1,
+                            line.trim(),
+                            reason
+                        );
+                    }
+                    None => {}
+                }
+            }
 
-            Ok(file_info)
+            trace!(
+                "{}: {} include(s) kept, {} dynamic",
+                abs_path,
+                includes.len(),
+                dynamic_includes.len()
+            );
+
+            Ok(ScannedIncludes {
+                includes,
+                dynamic_includes,
+                skipped_generated_includes,
+                guarded: Self::is_include_guard(&first_directives),
+            })
         }
 
-        pub fn get_file(
-            &mut self,
-            partial_path: &str,
-            entry_module: &str,
-        ) -> Result<Rc<RefCell<FileInfo>>> {
-            // Check if root module actually exists
-            let mut root_module = None;
+        // Stand-in for an include target that exists but couldn't be parsed, so the include
+        // doesn't vanish from the graph and the report can tell why the branch ends there
+        pub fn leaf(
+            abs_path: &str,
+            modules: &[(String, Vec<String>)],
+            reason: &str,
+            scan_options: &ScanOptions,
+            path_pool: &mut PathPool,
+        ) -> Rc<RefCell<FileInfo>> {
+            let file_name = file_name_of(abs_path);
+            let file_type = file_name
+                .split('.')
+                .next_back()
+                .and_then(|extension| scan_options.file_type(extension))
+                .unwrap_or(FileType::Unsupported);
 
-            for modl in self.modules.clone() {
-                if modl.0 == entry_module {
-                    root_module = Some(modl);
-                    break;
+            Rc::new(RefCell::new(Self {
+                abs_path: path_pool.intern(abs_path),
+                file_name: path_pool.intern(file_name),
+                module: path_pool.intern(&Self::find_module(abs_path, modules).unwrap_or_default()),
+                file_type,
+                includes: vec![],
+                dynamic_includes: vec![],
+                skipped_generated_includes: 0,
+                guarded: false,
+                processed: false,
+                reached: false,
+                cycle_free: false,
+                leaf_reason: Some(reason.to_string()),
+                external: false,
+                resolved_includes: BTreeSet::new(),
+                unresolved_includes: BTreeSet::new(),
+                include_edges: BTreeMap::new(),
+            }))
+        }
+
+        // The module with the longest include path containing the file, so nested modules win
+        // (ties go to the one listed first). Otherwise the longest module name in the path, for
+        // include paths written differently than `abs_path`
+        pub fn find_module(abs_path: &str, modules: &[(String, Vec<String>)]) -> Option<String> {
+            let abs_path = normalize_separators(abs_path);
+
+            modules
+                .iter()
+                .rev()
+                .filter_map(|modl| {
+                    modl.1
+                        .iter()
+                        .map(|include_path| normalize_separators(include_path))
+                        .filter(|include_path| Path::new(&abs_path).starts_with(include_path))
+                        .map(|include_path| Path::new(&include_path).components().count())
+                        .max()
+                        .map(|depth| (modl, depth))
+                })
+                .max_by_key(|(_modl, depth)| *depth)
+                .map(|(modl, _depth)| modl)
+                .or_else(|| {
+                    modules
+                        .iter()
+                        .rfind(|(modl, _include_paths)| abs_path.contains(modl.as_str()))
+                })
+                .map(|(modl, _include_paths)| modl.clone())
+        }
+
+        // The line split into its code and its comments, in one pass so comment markers in
+        // literals and raw string openers in comments don't count. Raw string literal contents
+        // (`R"delim(...)delim"`) are dropped, a block comment is replaced by a space in the code
+        // like the preprocessor does. `state` carries what's still open over to the next line
+        fn split_comments(line: &str, state: &mut LineState) -> (String, String) {
+            let (mut code, mut comments) = (String::new(), String::new());
+            let mut rest = line;
+
+            'line: loop {
+                if let Some(delimiter) = state.raw_string_delimiter.as_deref() {
+                    let closing = format!("){}\"", delimiter);
+
+                    match rest.find(&closing) {
+                        Some(closing_ind) => {
+                            rest = &rest[closing_ind + closing.len()..];
+                            state.raw_string_delimiter = None;
+                        }
+                        None => return (code, comments),
+                    }
                 }
-            }
 
-            // If it does
-            if root_module.is_some() {
-                let modl = root_module.clone().unwrap();
+                if state.in_block_comment {
+                    match rest.find("*/") {
+                        Some(closing_ind) => {
+                            comments.push_str(&rest[..closing_ind]);
+                            rest = &rest[closing_ind + 2..];
+                            state.in_block_comment = false;
+                        }
+                        None => {
+                            comments.push_str(rest);
+                            return (code, comments);
+                        }
+                    }
+                }
 
-                if let std::result::Result::Ok(file) = self.get_file_in_module(modl, partial_path) {
-                    return Ok(file);
+                let mut ind = 0;
+
+                while let Some(c) = rest[ind..].chars().next() {
+                    let tail = &rest[ind..];
+
+                    if let Some(line_comment) = tail.strip_prefix("//") {
+                        code.push_str(&rest[..ind]);
+                        comments.push_str(line_comment);
+                        return (code, comments);
+                    }
+
+                    if let Some(after_opening) = tail.strip_prefix("/*") {
+                        code.push_str(&rest[..ind]);
+                        code.push(' ');
+                        state.in_block_comment = true;
+                        rest = after_opening;
+                        continue 'line;
+                    }
+
+                    match c {
+                        '"' => match Self::raw_string_opening(rest, ind) {
+                            Some((opening_ind, contents_start, delimiter)) => {
+                                code.push_str(&rest[..opening_ind]);
+                                state.raw_string_delimiter = Some(delimiter);
+                                rest = &rest[contents_start..];
+                                continue 'line;
+                            }
+                            None => ind = Self::literal_end(rest, ind, '"'),
+                        },
+                        // `1'000'000` is a digit separator, not a character literal
+                        '\'' if !Self::ends_with_number(&rest[..ind]) => {
+                            ind = Self::literal_end(rest, ind, '\'')
+                        }
+                        _ => ind += c.len_utf8(),
+                    }
                 }
+
+                code.push_str(rest);
+                return (code, comments);
             }
+        }
 
-            let other_modules: Vec<(String, Vec<String>)> = if let Some(root_mod) = root_module {
-                self.modules
-                    .iter()
-                    .filter(|(modl, _include_paths)| modl != &root_mod.0)
-                    .cloned()
-                    .collect()
-            } else {
-                self.modules.clone()
-            };
+        // For the `"` at `quote_ind`: where the `R` (and its encoding prefix) starts, where the
+        // contents start and the delimiter, if it opens a raw string literal
+        fn raw_string_opening(line: &str, quote_ind: usize) -> Option<(usize, usize, String)> {
+            let before = line[..quote_ind].strip_suffix('R')?;
+            let prefix_start = ["u8", "u", "U", "L", ""]
+                .iter()
+                .find_map(|prefix| before.strip_suffix(prefix))?;
 
-            for module in other_modules {
-                if let std::result::Result::Ok(file) = self.get_file_in_module(module, partial_path)
-                {
-                    return Ok(file);
+            // `FOOR"` isn't a raw string
+            if prefix_start
+                .chars()
+                .next_back()
+                .is_some_and(|c| c.is_alphanumeric() || c == '_')
+            {
+                return None;
+            }
+
+            let after = &line[quote_ind + 1..];
+            let delimiter_end = after.find('(')?;
+            let delimiter = &after[..delimiter_end];
+
+            let is_valid_delimiter = delimiter.len() <= 16
+                && !delimiter.contains(|c: char| c.is_whitespace() || "()\\\"".contains(c));
+
+            is_valid_delimiter.then(|| {
+                (
+                    quote_ind - 1,
+                    quote_ind + 1 + delimiter_end + 1,
+                    delimiter.to_owned(),
+                )
+            })
+        }
+
+        // Just past the closing quote of the string or character literal opening at `quote_ind`,
+        // the end of the line if it isn't closed
+        fn literal_end(line: &str, quote_ind: usize, quote: char) -> usize {
+            let mut chars = line[quote_ind + 1..].char_indices();
+
+            while let Some((ind, c)) = chars.next() {
+                match c {
+                    '\\' => {
+                        chars.next();
+                    }
+                    c if c == quote => return quote_ind + 1 + ind + c.len_utf8(),
+                    _ => {}
                 }
             }
 
-            bail!("Couldn't get the file");
+            line.len()
         }
 
-        fn get_file_in_module(
-            &mut self,
-            modl: (String, Vec<String>),
-            partial_path: &str,
-        ) -> Result<Rc<RefCell<FileInfo>>> {
-            // Check if any of the paths inside of the module are viable for the file we're looking
-            // for
-            for include_path in modl.1.iter() {
-                // Concatenating the include path and partial path
-                let path_to_file = format!("{}/{}", include_path, partial_path);
+        // Whether the text ends with a number literal, its last token starting with a digit
+        fn ends_with_number(text: &str) -> bool {
+            text.rsplit(|c: char| !(c.is_alphanumeric() || c == '_' || c == '\''))
+                .next()
+                .and_then(|token| token.chars().next())
+                .is_some_and(|c| c.is_ascii_digit())
+        }
 
-                // If path exists on the computer
-                if Path::new(path_to_file.as_str()).exists() {
-                    // Return cached file info if it exists
-                    return if let Some(file) = self
-                        .files
-                        .iter()
-                        .find(|f| (*f).borrow().abs_path == path_to_file)
-                    {
-                        Ok(file.clone())
-                    } else {
-                        // If it doesnt, create new file info, cache it and return it
-                        Ok(self.create_file_info(&path_to_file)?)
-                    };
+        // The first two directives of a file split into words, without the `#`
+        fn is_include_guard(first_directives: &[Vec<String>]) -> bool {
+            match first_directives {
+                [first, ..] if first[..] == ["pragma", "once"] => true,
+                [first, second] => {
+                    first.len() == 2
+                        && first[0] == "ifndef"
+                        && second.len() >= 2
+                        && second[0] == "define"
+                        && first[1] == second[1]
                 }
+                _ => false,
             }
+        }
 
-            bail!("Couldn't get the file in module")
+        fn is_code_line(line: &str) -> bool {
+            let line = line.trim();
+
+            !(line.is_empty()
+                || line.starts_with("//")
+                || line.starts_with("/*")
+                || line.starts_with('*')
+                || line.starts_with('#'))
         }
     }
 
-    impl Debug for Project {
+    impl Debug for FileInfo {
         fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-            writeln!(f, "Project [")?;
-            writeln!(f, "\tRoot Path: {}", self.root_path)?;
-            writeln!(f, "\tModules: [")?;
-            for module in self.modules.iter() {
-                writeln!(f, "\t\t(")?;
-                writeln!(f, "\t\t\tModule: {}", module.0)?;
-                writeln!(f, "\t\t\tInclude Paths: [")?;
-                for include_path in module.1.iter() {
-                    writeln!(f, "\t\t\t\t{},", include_path)?;
-                }
-                writeln!(f, "\t\t\t]")?;
-                writeln!(f, "\t\t)")?;
+            writeln!(f, "FileInfo(")?;
+            writeln!(f, "\tAbsolute Path: {}", self.abs_path)?;
+            writeln!(f, "\tFile Name: {}", self.file_type)?;
+            writeln!(f, "\tModule: {}", self.module)?;
+            writeln!(f, "\tFile Type: {}", self.file_type)?;
+            writeln!(
+                f,
+                "\tIncludes: {:?}",
+                self.includes
+                    .iter()
+                    .map(|include| &include.line)
+                    .collect::<Vec<_>>()
+            )?;
+            writeln!(f, "\tDynamic Includes: {:?}", self.dynamic_includes)?;
+            writeln!(
+                f,
+                "\tSkipped Generated Includes: {}",
+                self.skipped_generated_includes
+            )?;
+            writeln!(f, "\tGuarded: {}", self.guarded)?;
+            writeln!(f, "\tProcessed: {}", self.processed)?;
+            writeln!(f, "\tReached: {}", self.reached)?;
+            writeln!(f, "\tCycle Free: {}", self.cycle_free)?;
+            writeln!(f, "\tLeaf Reason: {:?}", self.leaf_reason)?;
+            writeln!(f, "\tExternal: {}", self.external)?;
+            writeln!(f, "\tResolved Includes: {:?}", self.resolved_includes)?;
+            writeln!(f, "\tUnresolved Includes: {:?}", self.unresolved_includes)?;
+            writeln!(f, "\tInclude Edges: {:?}", self.include_edges)?;
+            writeln!(f, ")")
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::{project::Project, test_fixture::Fixture};
+
+        #[test]
+        fn unreadable_line_fails_the_parse() {
+            let fixture = Fixture::empty();
+            let header =
+                fixture.write_bytes("Foo.h", b"#include \"A.h\"\n\xff\xfe\n#include \"B.h\"\n");
+
+            let error = FileInfo::create(
+                &header,
+                &[],
+                &ScanOptions::default(),
+                &DefaultIncludeScanner,
+                &mut PathPool::default(),
+            )
+            .unwrap_err();
+
+            assert!(format!("{:#}", error).contains("Couldn't read line 2"));
+        }
+
+        // Includes of a header with `contents`, parsed like any project file
+        fn includes_of(contents: &str) -> Vec<Include> {
+            let fixture = Fixture::new(&["Engine/Source/Runtime/Core"]);
+            let header = fixture.write("Engine/Source/Runtime/Core/Public/Foo.h", contents);
+
+            let file_info = Project::create(fixture.root())
+                .unwrap()
+                .entry_file_info(&header)
+                .unwrap();
+            let includes = (*file_info).borrow().includes.clone();
+
+            includes
+        }
+
+        #[test]
+        fn continuation_split_include_is_joined() {
+            assert_eq!(
+                includes_of("#include \\\n\"Split.h\"\n#include \"After.h\"\n")
+                    .iter()
+                    .map(|include| (include.path.as_str(), include.line_number))
+                    .collect::<Vec<_>>(),
+                [("Split.h", 1), ("After.h", 3)]
+            );
+        }
+
+        #[test]
+        fn quoted_and_angle_bracket_imports_are_dependencies() {
+            let includes =
+                includes_of("import \"Quoted.h\";\nimport <Angled.h>;\n#include \"Included.h\"\n");
+
+            assert_eq!(
+                includes
+                    .iter()
+                    .map(|include| (include.path.as_str(), include.kind))
+                    .collect::<Vec<_>>(),
+                [
+                    ("Quoted.h", IncludeKind::Import),
+                    ("Angled.h", IncludeKind::Import),
+                    ("Included.h", IncludeKind::Include)
+                ]
+            );
+        }
+
+        #[test]
+        fn include_with_the_ignore_marker_is_dropped() {
+            let includes = includes_of(
+                "#include \"Kept.h\"\n#include \"Ignored.h\" // uecds:ignore\n#include \"AlsoKept.h\"\n",
+            );
+
+            assert_eq!(
+                includes
+                    .iter()
+                    .map(|include| include.path.as_str())
+                    .collect::<Vec<_>>(),
+                ["Kept.h", "AlsoKept.h"]
+            );
+        }
+
+        #[test]
+        fn ignore_marker_outside_of_a_comment_is_part_of_the_include() {
+            let includes = includes_of(
+                "#include \"uecds:ignore/Foo.h\" // a // in the path: \"//\"\n#include \"Bar.h\" /* '\"' */ // uecds:ignore\n",
+            );
+
+            assert_eq!(
+                includes
+                    .iter()
+                    .map(|include| include.path.as_str())
+                    .collect::<Vec<_>>(),
+                ["uecds:ignore/Foo.h"]
+            );
+        }
+
+        #[test]
+        fn includes_inside_raw_strings_are_skipped() {
+            let includes = includes_of(
+                "#include \"Real.h\"\n\
+                 const char* Shader = R\"hlsl(\n\
+                 #include \"Fake.ush\"\n\
+                 )hlsl\";\n\
+                 const char* Inline = R\"(#include \"AlsoFake.h\")\";\n\
+                 #include \"AlsoReal.h\"\n",
+            );
+
+            assert_eq!(
+                includes
+                    .iter()
+                    .map(|include| include.path.as_str())
+                    .collect::<Vec<_>>(),
+                ["Real.h", "AlsoReal.h"]
+            );
+        }
+
+        #[test]
+        fn raw_string_opener_in_a_comment_is_ignored() {
+            let includes = includes_of(
+                "// Shaders go in R\"hlsl( literals\n\
+                 #include \"Real.h\"\n\
+                 const char* Quoted = \"R\\\"(\"; /* R\"( */\n\
+                 #include \"AlsoReal.h\"\n",
+            );
+
+            assert_eq!(
+                includes
+                    .iter()
+                    .map(|include| include.path.as_str())
+                    .collect::<Vec<_>>(),
+                ["Real.h", "AlsoReal.h"]
+            );
+        }
+
+        #[test]
+        fn quotes_in_character_literals_dont_hide_comments() {
+            let includes = includes_of(
+                "const char Quote = '\"'; // #include \"InLineComment.h\"\n\
+                 const char Slash = '/'; /* #include \"InBlockComment.h\" */\n\
+                 const int Big = 1'000'000; // #include \"AfterSeparators.h\"\n\
+                 #include \"Real.h\"\n",
+            );
+
+            assert_eq!(
+                includes
+                    .iter()
+                    .map(|include| include.path.as_str())
+                    .collect::<Vec<_>>(),
+                ["Real.h"]
+            );
+        }
+
+        #[test]
+        fn nested_module_wins_no_matter_the_order() {
+            let outer = (
+                "Engine/Source/Runtime/Outer".to_string(),
+                vec!["/UE/Engine/Source/Runtime/Outer/Public".to_string()],
+            );
+            let inner = (
+                "Engine/Source/Runtime/Outer/Public/Inner".to_string(),
+                vec!["/UE/Engine/Source/Runtime/Outer/Public/Inner/Public".to_string()],
+            );
+
+            for modules in [
+                vec![outer.clone(), inner.clone()],
+                vec![inner.clone(), outer.clone()],
+            ] {
+                assert_eq!(
+                    FileInfo::find_module(
+                        "/UE/Engine/Source/Runtime/Outer/Public/Inner/Public/Foo.h",
+                        &modules
+                    ),
+                    Some(inner.0.clone())
+                );
+                assert_eq!(
+                    FileInfo::find_module("/UE/Engine/Source/Runtime/Outer/Public/Bar.h", &modules),
+                    Some(outer.0.clone())
+                );
             }
-            writeln!(f, "\t]")?;
-            writeln!(f, "\tfiles: {:?}", self.files)?;
-            writeln!(f, "]")
+        }
+
+        #[test]
+        fn parse_includes_lists_the_paths_as_written() {
+            let fixture = Fixture::empty();
+            let header = fixture.write(
+                "Foo.h",
+                "#pragma once\n\n#include \"CoreMinimal.h\"\n#include <vector>\n#include \"Foo/Bar.h\"\n",
+            );
+
+            assert_eq!(
+                parse_includes(&header).unwrap(),
+                ["CoreMinimal.h", "vector", "Foo/Bar.h"]
+            );
+        }
+
+        #[test]
+        fn commented_out_includes_are_skipped() {
+            let includes = includes_of(
+                "// #include \"LineComment.h\"\n\
+                 /* #include \"SameLineBlock.h\" */\n\
+                 /*\n\
+                 #include \"InsideBlock.h\"\n\
+                 */\n\
+                 #include \"Real.h\" // trailing comment\n\
+                 /* before */ #include \"AfterBlock.h\"\n\
+                 \t// #include \"IndentedLineComment.h\"\n",
+            );
+
+            assert_eq!(
+                includes
+                    .iter()
+                    .map(|include| include.path.as_str())
+                    .collect::<Vec<_>>(),
+                ["Real.h", "AfterBlock.h"]
+            );
+        }
+
+        #[test]
+        fn backslash_paths_give_the_file_name_and_module() {
+            let modules = vec![(
+                "Engine/Source/Runtime/Core".to_string(),
+                vec!["C:/UnrealEngine/Engine/Source/Runtime/Core/Public".to_string()],
+            )];
+            let abs_path = "C:\\UnrealEngine\\Engine\\Source\\Runtime\\Core\\Public\\Foo.h";
+
+            assert_eq!(file_name_of(abs_path), "Foo.h");
+            assert_eq!(
+                FileInfo::find_module(abs_path, &modules).as_deref(),
+                Some("Engine/Source/Runtime/Core")
+            );
         }
     }
 }
 
-use std::{fs::File, io::Write, path::Path, rc::Rc};
+pub mod node {
+    use std::{
+        cell::RefCell,
+        collections::{HashMap, HashSet},
+        fmt::{Debug, Formatter},
+        rc::Rc,
+    };
+
+    use crate::{
+        file_info::{FileInfo, FileType, IncludeKind},
+        project::Project,
+    };
 
-use anyhow::*;
-use itertools::Itertools;
+    pub const DEFAULT_MAX_CYCLES: usize = 10_000;
 
-use crate::{node::Node, project::Project};
+    #[derive(Clone, Debug)]
+    pub struct TraverseOptions {
+        // Only includes resolving to these file types become nodes, `None` allows all of them
+        pub allowed_file_types: Option<HashSet<FileType>>,
+        // Only the entry point is expanded if it's a source file, sources included from anywhere
+        // else don't become nodes. Translation units pull headers in, the cycles live among those
+        pub root_sources_only: bool,
+        // Includes resolving to files of these modules don't become nodes, e.g. third party
+        // modules whose cycles can't be fixed anyway
+        pub excluded_modules: HashSet<String>,
+        // Only expand files of this module, includes into any other one end the branch. Answers
+        // "is this module circular in itself" without walking the rest of the engine
+        pub only_module: Option<String>,
+        // File name patterns (`*` matches anything, case insensitive) of precompiled header
+        // wrappers like `*PCH*.h`, their bulk includes would add a huge artificial fan-out, so
+        // they aren't expanded unless they're the entry point
+        pub pch_patterns: Vec<String>,
+        // Don't follow C++20 header unit imports, only `#include`s
+        pub skip_imports: bool,
+        // Recursive paths hold the absolute paths of the files instead of just their names
+        pub abs_paths: bool,
+        // Stop at the first recursive path found, for when only "is there any cycle" matters
+        pub fail_fast: bool,
+        // Recursive paths only hold the cycle itself, from the first occurrence of the repeated
+        // file on. Paths reaching the same cycle from different entry branches collapse into one
+        pub minimal_cycles: bool,
+        // Stop collecting recursive paths after this many, badly tangled projects can otherwise
+        // produce tens of thousands of them. Traversal still finishes
+        pub max_cycles: Option<usize>,
+        // Don't expand files this many includes deep, an escape hatch for pathological include
+        // trees. Cycles going deeper than that are missed
+        pub max_depth: Option<usize>,
+    }
 
-pub const CACHE_CONFIG_PATH: &str = "./.cache";
+    impl Default for TraverseOptions {
+        fn default() -> Self {
+            Self {
+                allowed_file_types: None,
+                root_sources_only: false,
+                excluded_modules: HashSet::new(),
+                only_module: None,
+                pch_patterns: vec![],
+                skip_imports: false,
+                abs_paths: false,
+                fail_fast: false,
+                minimal_cycles: false,
+                max_cycles: Some(DEFAULT_MAX_CYCLES),
+                max_depth: None,
+            }
+        }
+    }
+
+    // Index into `NodeTree::nodes`
+    pub type NodeId = usize;
+
+    #[derive(Eq)]
+    pub struct Node {
+        file_info: Rc<RefCell<FileInfo>>,
+        prev: Option<NodeId>,
+        children: Vec<NodeId>,
+        found_cycle: bool,
+        // `max_depth` cut its subtree short somewhere. Its file mustn't be marked processed then,
+        // reached on a shorter path it has to be expanded again
+        depth_limited: bool,
+        // Done with, for the nodes whose file can't be marked processed
+        finished: bool,
+    }
 
-pub fn find_rec_deps(project_path: &str, entry_point: &str, output_file_path: &str) -> Result<()> {
-    let mut project = Project::create(project_path)?;
-    let entry_point_file_info = Rc::new(project.create_file_info(entry_point)?);
+    // Every node of a traversal, parents and children refer to each other by index. A node's
+    // path is only rebuilt from the parent links when it closes a cycle, so deep include chains
+    // don't keep a copy of the whole path per node
+    #[derive(Debug, Default)]
+    pub struct NodeTree {
+        nodes: Vec<Node>,
+    }
 
-    let root_node = Node::create(&entry_point_file_info, None);
+    impl NodeTree {
+        pub fn create(entry_point: &Rc<RefCell<FileInfo>>) -> Self {
+            let mut tree = Self::default();
+            tree.add_node(entry_point, None);
 
-    let recursive_paths = Node::traverse(&root_node, &mut project);
+            tree
+        }
 
-    let mut file = File::create(Path::new(&output_file_path))?;
+        fn add_node(&mut self, file_info: &Rc<RefCell<FileInfo>>, prev: Option<NodeId>) -> NodeId {
+            // A self include's file is still borrowed by `create_node_children`
+            if !(*file_info).borrow().reached {
+                (*file_info).borrow_mut().reached = true;
+            }
 
-    for (file_name, paths) in recursive_paths.iter() {
-        file.write_all(b"------------------------------------------------\n")?;
+            self.nodes.push(Node {
+                file_info: file_info.clone(),
+                prev,
+                children: vec![],
+                found_cycle: false,
+                depth_limited: false,
+                finished: false,
+            });
 
-        file.write_all((format!("{}:\n", file_name)).as_bytes())?;
+            self.nodes.len() - 1
+        }
 
-        let output_paths: Vec<&Vec<String>> = paths
-            .iter()
-            .sorted_by(|path1, path2| Ord::cmp(&path1.len(), &path2.len()))
-            .collect();
+        pub fn traverse(
+            &mut self,
+            project: &mut Project,
+            options: &TraverseOptions,
+        ) -> HashMap<String, HashSet<Vec<String>>> {
+            let mut recursive_paths = HashMap::new();
+            self.traverse_into(project, options, &mut recursive_paths);
 
-        for path in output_paths {
-            file.write_all(format!("\t{}\n", path.join("->")).as_bytes())?;
+            recursive_paths
         }
 
-        file.write_all("------------------------------------------------\n".as_bytes())?;
-    }
+        // Adds the recursive paths found to `recursive_paths`. `max_cycles` counts the ones
+        // already in there as well, so the limit can be shared between entry points. Returns
+        // whether a path was left out because of it
+        #[instrument(
+            skip_all,
+            fields(entry_point = %(*self.nodes[0].file_info).borrow().abs_path)
+        )]
+        pub fn traverse_into(
+            &mut self,
+            project: &mut Project,
+            options: &TraverseOptions,
+            recursive_paths: &mut HashMap<String, HashSet<Vec<String>>>,
+        ) -> bool {
+            let mut collected_paths: usize =
+                recursive_paths.values().map(|paths| paths.len()).sum();
+            let mut truncated = false;
+            let mut truncated_branches = 0;
 
-    let mut config_file = File::create(CACHE_CONFIG_PATH)?;
-    config_file
-        .write_all(format!("{}\n{}\n{}", project_path, entry_point, output_file_path).as_bytes())?;
+            // The starting node, every other one comes from `create_node_children`
+            project.metrics.nodes_created += 1;
 
-    Ok(())
+            let mut current: NodeId = 0;
+            // Files of the nodes from the root to `current`, paths are interned so the pointers
+            // are enough
+            let mut current_path_files = HashSet::from([self.path_ptr(current)]);
+
+            loop {
+                let current_processed = (*self.nodes[current].file_info).borrow().processed
+                    || self.nodes[current].finished;
+
+                // If the current node is already processed
+                if current_processed {
+                    let current_prev = self.nodes[current].prev;
+                    let current_found_cycle = self.nodes[current].found_cycle;
+                    let current_depth_limited = self.nodes[current].depth_limited;
+
+                    // If no cycle was found below it, the whole subtree is clean and
+                    // doesn't need to be looked at again
+                    if !current_found_cycle {
+                        (*self.nodes[current].file_info).borrow_mut().cycle_free = true;
+                    }
+
+                    // Go Back
+                    if let Some(previous) = current_prev {
+                        current_path_files.remove(&self.path_ptr(current));
+                        self.nodes[previous].found_cycle |= current_found_cycle;
+                        self.nodes[previous].depth_limited |= current_depth_limited;
+                        current = previous;
+                        continue;
+                    } else {
+                        break;
+                    }
+                }
+
+                // If it's not yet fully processed
+                // Check if it's too deep to be expanded
+                if let Some(max_depth) = options.max_depth {
+                    if current_path_files.len() >= max_depth {
+                        debug!(
+                            "Not going deeper than {}: {:?}",
+                            max_depth,
+                            self.readable_path(current, options.abs_paths, false)
+                        );
+                        truncated_branches += 1;
+
+                        // Its subtree wasn't looked at, so it mustn't be taken as cycle free
+                        self.nodes[current].found_cycle = true;
+                        self.nodes[current].depth_limited = true;
+                        self.nodes[current].finished = true;
+                        continue;
+                    }
+                }
+
+                // Check if the node has children
+                if self.nodes[current].children.is_empty() {
+                    // If the doesn't have children yet
+                    // Check if the file o the node actually has any includes
+                    let current_file_info = self.nodes[current].file_info.clone();
+                    if !(*current_file_info).borrow().includes.is_empty() {
+                        // If there are any includes, create node children
+                        self.create_node_children(current, project, options);
+                    } else {
+                        // If there was non in the first place, we can count this node as a processed
+                        // one and skip loop iteration
+                        (*current_file_info).borrow_mut().processed = true;
+                        continue;
+                    }
+                }
+
+                // If the node has children, lets fine an unprocessed one
+                if let Some(unprocessed_child) =
+                    self.nodes[current].children.iter().copied().find(|&child| {
+                        !(*self.nodes[child].file_info).borrow().processed
+                            && !self.nodes[child].finished
+                    })
+                {
+                    // If we find one, we check if it's not a recursive one
+                    if current_path_files.contains(&self.path_ptr(unprocessed_child)) {
+                        // If it is recursive, it can be considered processed right away and we print
+                        // out its path
+                        (*self.nodes[unprocessed_child].file_info)
+                            .borrow_mut()
+                            .processed = true;
+                        self.nodes[current].found_cycle = true;
+
+                        let readable_path = self.readable_path(
+                            unprocessed_child,
+                            options.abs_paths,
+                            options.minimal_cycles,
+                        );
+
+                        let key = readable_path.last().unwrap().clone();
+
+                        if !recursive_paths
+                            .get(&key)
+                            .is_some_and(|paths| paths.contains(&readable_path))
+                        {
+                            if options
+                                .max_cycles
+                                .is_some_and(|max_cycles| collected_paths >= max_cycles)
+                            {
+                                truncated = true;
+                                continue;
+                            }
+
+                            recursive_paths
+                                .entry(key)
+                                .or_default()
+                                .insert(readable_path.clone());
+                            collected_paths += 1;
+
+                            if Some(collected_paths) == options.max_cycles {
+                                warn!(
+                                    "Reached the limit of {} recursive paths, not collecting any more",
+                                    collected_paths
+                                );
+                            }
+                        }
+
+                        info!("RECURSIVE PATH FOUND: {:?}", readable_path);
+
+                        if options.fail_fast {
+                            break;
+                        }
+                    } else {
+                        // If it isn't, we can go deeper into the tree
+                        current_path_files.insert(self.path_ptr(unprocessed_child));
+                        current = unprocessed_child;
+                    }
+                } else {
+                    // If there's none left, we can call this node processed and skip the loop iteration
+                    if self.nodes[current].depth_limited {
+                        self.nodes[current].finished = true;
+                    } else {
+                        (*self.nodes[current].file_info).borrow_mut().processed = true;
+                    }
+                }
+            }
+
+            if truncated_branches > 0 {
+                warn!(
+                    "Stopped {} branch(es) at the maximum depth of {}",
+                    truncated_branches,
+                    options.max_depth.unwrap_or_default()
+                );
+            }
+
+            truncated
+        }
+
+        fn create_node_children(
+            &mut self,
+            node: NodeId,
+            project: &mut Project,
+            options: &TraverseOptions,
+        ) {
+            let file_info = self.nodes[node].file_info.clone();
+            let (mut resolved_includes, mut unresolved_includes) = (vec![], vec![]);
+            let mut include_edges = vec![];
+
+            let node_children = (*file_info)
+                .borrow()
+                .includes
+                .iter()
+                .filter(|include| !(options.skip_imports && include.kind == IncludeKind::Import))
+                .filter_map(|include| {
+                    match project.get_file(&include.path, &(*file_info).borrow().module) {
+                        Ok(include_file_info) => {
+                            resolved_includes.push(include.path.clone());
+                            include_edges.push((
+                                (*include_file_info).borrow().abs_path.clone(),
+                                include.clone(),
+                            ));
+
+                            // Reported by `traverse` like any other cycle, none of the filters
+                            // below should hide it
+                            if Rc::ptr_eq(&include_file_info, &file_info) {
+                                warn!("{} includes itself", (*file_info).borrow().abs_path);
+
+                                return Some(self.add_node(&include_file_info, Some(node)));
+                            }
+
+                            if (*include_file_info).borrow().cycle_free {
+                                return None;
+                            }
+
+                            if let Some(allowed_file_types) = &options.allowed_file_types {
+                                let file_type = (*include_file_info).borrow().file_type;
+
+                                if !allowed_file_types.contains(&file_type) {
+                                    return None;
+                                }
+                            }
+
+                            // Children are never the root
+                            if options.root_sources_only
+                                && (*include_file_info).borrow().file_type == FileType::Source
+                            {
+                                return None;
+                            }
+
+                            if options
+                                .excluded_modules
+                                .contains(&*(*include_file_info).borrow().module)
+                            {
+                                return None;
+                            }
+
+                            if let Some(only_module) = &options.only_module {
+                                if *(*include_file_info).borrow().module != **only_module {
+                                    return None;
+                                }
+                            }
+
+                            if options.pch_patterns.iter().any(|pch_pattern| {
+                                matches_pattern(
+                                    pch_pattern,
+                                    &(*include_file_info).borrow().file_name,
+                                )
+                            }) {
+                                return None;
+                            }
+
+                            Some(self.add_node(&include_file_info, Some(node)))
+                        }
+                        Err(_) => {
+                            unresolved_includes.push(include.path.clone());
+                            None
+                        }
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            let mut file_info = (*file_info).borrow_mut();
+            file_info.resolved_includes.extend(resolved_includes);
+            file_info.unresolved_includes.extend(unresolved_includes);
+            for (abs_path, include) in include_edges {
+                file_info.include_edges.entry(abs_path).or_insert(include);
+            }
+
+            project.metrics.nodes_created += node_children.len();
+            self.nodes[node].children = node_children;
+        }
+
+        fn path_ptr(&self, node: NodeId) -> *const str {
+            Rc::as_ptr(&(*self.nodes[node].file_info).borrow().abs_path)
+        }
+
+        // From the root down to `node`, following the parent links. `minimal` stops at the
+        // ancestor of the same file instead, leaving only the cycle `node` closes
+        fn readable_path(&self, node: NodeId, abs_paths: bool, minimal: bool) -> Vec<String> {
+            let cycle_file = &self.nodes[node].file_info;
+            let mut readable_path = vec![];
+            let mut current = Some(node);
+
+            while let Some(ancestor) = current {
+                readable_path.push(Self::readable_name(
+                    &self.nodes[ancestor].file_info,
+                    abs_paths,
+                ));
+
+                if minimal
+                    && ancestor != node
+                    && Rc::ptr_eq(&self.nodes[ancestor].file_info, cycle_file)
+                {
+                    break;
+                }
+
+                current = self.nodes[ancestor].prev;
+            }
+
+            readable_path.reverse();
+            readable_path
+        }
+
+        fn readable_name(file_info: &Rc<RefCell<FileInfo>>, abs_paths: bool) -> String {
+            let file_info = (*file_info).borrow();
+
+            match abs_paths {
+                true => file_info.abs_path.to_string(),
+                false => file_info.file_name.to_string(),
+            }
+        }
+    }
+
+    // `*` matches any run of characters, everything else itself (ignoring case)
+    fn matches_pattern(pattern: &str, file_name: &str) -> bool {
+        let (pattern, file_name) = (pattern.to_lowercase(), file_name.to_lowercase());
+        let mut parts = pattern.split('*');
+
+        // Without a `*` the first part is the whole pattern
+        let first = parts.next().unwrap_or_default();
+        let mut rest = match file_name.strip_prefix(first) {
+            Some(rest) => rest,
+            None => return false,
+        };
+
+        let parts = parts.collect::<Vec<_>>();
+        let last = match parts.split_last() {
+            Some((last, middle)) => {
+                for part in middle {
+                    match rest.find(part) {
+                        Some(part_ind) => rest = &rest[part_ind + part.len()..],
+                        None => return false,
+                    }
+                }
+                last
+            }
+            None => return rest.is_empty(),
+        };
+
+        rest.ends_with(last)
+    }
+
+    impl PartialEq for Node {
+        fn eq(&self, other: &Self) -> bool {
+            self.file_info == other.file_info && self.prev == other.prev
+        }
+    }
+
+    impl Debug for Node {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            writeln!(f, "Node (")?;
+            writeln!(f, "\tFile Info: {}", (*self.file_info).borrow().abs_path)?;
+            writeln!(f, "\tPrevious Node: {:?}", self.prev)?;
+            writeln!(f, "\tChildren: {:?}", self.children)?;
+            writeln!(f, "\tFound Cycle: {}", self.found_cycle)?;
+            writeln!(f, "\tDepth Limited: {}", self.depth_limited)?;
+            writeln!(f, ")")
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::test_fixture::{write_mutual_pairs, Fixture};
+
+        const CORE_PUBLIC: &str = "Engine/Source/Runtime/Core/Public";
+
+        fn traverse_fixture(
+            fixture: &Fixture,
+            entry_point: &str,
+            options: &TraverseOptions,
+        ) -> (NodeTree, HashMap<String, HashSet<Vec<String>>>) {
+            let mut project = Project::create(fixture.root()).unwrap();
+            let entry_point = project.entry_file_info(entry_point).unwrap();
+            let mut node_tree = NodeTree::create(&entry_point);
+            let recursive_paths = node_tree.traverse(&mut project, options);
+
+            (node_tree, recursive_paths)
+        }
+
+        #[test]
+        fn max_depth_stops_a_long_chain() {
+            let fixture = Fixture::new(&["Engine/Source/Runtime/Core"]);
+            for i in 0..10 {
+                fixture.write(
+                    &format!("{}/H{}.h", CORE_PUBLIC, i),
+                    &format!("#include \"H{}.h\"\n", (i + 1) % 10),
+                );
+            }
+
+            let options = TraverseOptions {
+                max_depth: Some(3),
+                ..TraverseOptions::default()
+            };
+            let (node_tree, recursive_paths) = traverse_fixture(
+                &fixture,
+                &fixture.path(&format!("{}/H0.h", CORE_PUBLIC)),
+                &options,
+            );
+
+            assert!(recursive_paths.is_empty());
+            assert_eq!(node_tree.nodes.len(), 3);
+        }
+
+        // `C.h` is too deep below `Long1.h`, that mustn't keep it from being expanded right below the
+        // entry point
+        #[test]
+        fn max_depth_only_cuts_the_branch_that_is_too_deep() {
+            let fixture = Fixture::new(&["Engine/Source/Runtime/Core"]);
+            let entry_point = fixture.write(
+                &format!("{}/Root.h", CORE_PUBLIC),
+                "#include \"Long1.h\"\n#include \"C.h\"\n",
+            );
+            fixture.write(
+                &format!("{}/Long1.h", CORE_PUBLIC),
+                "#include \"Long2.h\"\n",
+            );
+            fixture.write(&format!("{}/Long2.h", CORE_PUBLIC), "#include \"C.h\"\n");
+            fixture.write(&format!("{}/C.h", CORE_PUBLIC), "#include \"D.h\"\n");
+            fixture.write(&format!("{}/D.h", CORE_PUBLIC), "#include \"C.h\"\n");
+
+            let options = TraverseOptions {
+                max_depth: Some(4),
+                ..TraverseOptions::default()
+            };
+            let (_, recursive_paths) = traverse_fixture(&fixture, &entry_point, &options);
+
+            assert_eq!(
+                recursive_paths.get("C.h"),
+                Some(&HashSet::from([vec![
+                    "Root.h".to_string(),
+                    "C.h".to_string(),
+                    "D.h".to_string(),
+                    "C.h".to_string()
+                ]]))
+            );
+        }
+        // Traverses every entry point into the same paths like `find_rec_deps` does
+        fn traverse_entry_points(
+            fixture: &Fixture,
+            entry_points: &[String],
+            max_cycles: usize,
+        ) -> (usize, bool) {
+            let mut project = Project::create(fixture.root()).unwrap();
+            let options = TraverseOptions {
+                max_cycles: Some(max_cycles),
+                ..TraverseOptions::default()
+            };
+            let mut recursive_paths = HashMap::new();
+            let mut truncated = false;
+
+            for entry_point in entry_points {
+                let mut node_tree =
+                    NodeTree::create(&project.create_file_info(entry_point).unwrap());
+                truncated |= node_tree.traverse_into(&mut project, &options, &mut recursive_paths);
+            }
+
+            (
+                recursive_paths.values().map(|paths| paths.len()).sum(),
+                truncated,
+            )
+        }
+
+        #[test]
+        fn max_cycles_stops_a_single_entry_point_at_the_limit() {
+            let fixture = Fixture::new(&["Engine/Source/Runtime/Core"]);
+            let entry_point = write_mutual_pairs(&fixture, "Entry", 4);
+
+            assert_eq!(
+                traverse_entry_points(&fixture, &[entry_point], 2),
+                (2, true)
+            );
+        }
+
+        #[test]
+        fn max_cycles_is_shared_between_entry_points() {
+            let fixture = Fixture::new(&["Engine/Source/Runtime/Core"]);
+            let entry_points = [
+                write_mutual_pairs(&fixture, "First", 2),
+                write_mutual_pairs(&fixture, "Second", 2),
+            ];
+
+            assert_eq!(traverse_entry_points(&fixture, &entry_points, 3), (3, true));
+        }
+
+        #[test]
+        fn max_cycles_isnt_truncated_when_everything_fits() {
+            let fixture = Fixture::new(&["Engine/Source/Runtime/Core"]);
+            let entry_point = write_mutual_pairs(&fixture, "Entry", 2);
+
+            assert_eq!(
+                traverse_entry_points(&fixture, &[entry_point], 2),
+                (2, false)
+            );
+        }
+
+        // `Foo.cpp` -> `Bar.h` -> `Foo.h` is the `Foo` unit depending on `Bar` and back
+        #[test]
+        fn paired_units_report_the_cycle_through_the_header() {
+            let fixture = Fixture::new(&["Engine/Source/Runtime/Core"]);
+            let entry_point = fixture.write(
+                "Engine/Source/Runtime/Core/Private/Foo.cpp",
+                "#include \"Bar.h\"\n",
+            );
+            fixture.write("Engine/Source/Runtime/Core/Public/Foo.h", "");
+            fixture.write(
+                "Engine/Source/Runtime/Core/Public/Bar.h",
+                "#include \"Foo.h\"\n",
+            );
+
+            let traverse = |pair_units: bool| {
+                let mut project = Project::create(fixture.root()).unwrap();
+                project.pair_units = pair_units;
+                let mut node_tree =
+                    NodeTree::create(&project.entry_file_info(&entry_point).unwrap());
+
+                node_tree.traverse(&mut project, &TraverseOptions::default())
+            };
+
+            assert!(traverse(false).is_empty());
+            assert_eq!(
+                traverse(true)["Foo.h"],
+                HashSet::from([vec![
+                    "Foo.h".to_string(),
+                    "Bar.h".to_string(),
+                    "Foo.h".to_string()
+                ]])
+            );
+        }
+
+        #[test]
+        fn root_sources_only_expands_the_root_source_but_not_deeper_ones() {
+            let fixture = Fixture::new(&["Engine/Source/Runtime/Core"]);
+            let entry_point =
+                fixture.write(&format!("{}/Main.cpp", CORE_PUBLIC), "#include \"A.h\"\n");
+            fixture.write(&format!("{}/A.h", CORE_PUBLIC), "#include \"Impl.cpp\"\n");
+            fixture.write(&format!("{}/Impl.cpp", CORE_PUBLIC), "#include \"A.h\"\n");
+
+            let (_, recursive_paths) =
+                traverse_fixture(&fixture, &entry_point, &TraverseOptions::default());
+            assert_eq!(recursive_paths.len(), 1);
+
+            let options = TraverseOptions {
+                root_sources_only: true,
+                ..TraverseOptions::default()
+            };
+            let (node_tree, recursive_paths) = traverse_fixture(&fixture, &entry_point, &options);
+
+            assert!(recursive_paths.is_empty());
+            assert_eq!(
+                node_tree
+                    .nodes
+                    .iter()
+                    .map(|node| (*node.file_info).borrow().file_name.to_string())
+                    .collect::<Vec<_>>(),
+                ["Main.cpp", "A.h"]
+            );
+        }
+
+        // `A.h`<->`B.h` goes through `Other`, `C.h`<->`D.h` stays in `Core`
+        #[test]
+        fn only_module_keeps_the_cycles_inside_the_module() {
+            let fixture =
+                Fixture::new(&["Engine/Source/Runtime/Core", "Engine/Source/Runtime/Other"]);
+            let entry_point = fixture.write(
+                &format!("{}/Root.h", CORE_PUBLIC),
+                "#include \"A.h\"\n#include \"C.h\"\n",
+            );
+            fixture.write(&format!("{}/A.h", CORE_PUBLIC), "#include \"B.h\"\n");
+            fixture.write(
+                "Engine/Source/Runtime/Other/Public/B.h",
+                "#include \"A.h\"\n",
+            );
+            fixture.write(&format!("{}/C.h", CORE_PUBLIC), "#include \"D.h\"\n");
+            fixture.write(&format!("{}/D.h", CORE_PUBLIC), "#include \"C.h\"\n");
+
+            let cycle_keys = |options: &TraverseOptions| {
+                let mut keys = traverse_fixture(&fixture, &entry_point, options)
+                    .1
+                    .into_keys()
+                    .collect::<Vec<_>>();
+                keys.sort();
+
+                keys
+            };
+
+            assert_eq!(cycle_keys(&TraverseOptions::default()), ["A.h", "C.h"]);
+            assert_eq!(
+                cycle_keys(&TraverseOptions {
+                    only_module: Some("Engine/Source/Runtime/Core".to_string()),
+                    ..TraverseOptions::default()
+                }),
+                ["C.h"]
+            );
+        }
+
+        #[test]
+        fn self_include_is_its_own_cycle() {
+            let fixture = Fixture::new(&["Engine/Source/Runtime/Core"]);
+            let entry_point =
+                fixture.write(&format!("{}/Root.h", CORE_PUBLIC), "#include \"Foo.h\"\n");
+            fixture.write(
+                &format!("{}/Foo.h", CORE_PUBLIC),
+                "#pragma once\n#include \"Foo.h\"\n",
+            );
+
+            let (_, recursive_paths) =
+                traverse_fixture(&fixture, &entry_point, &TraverseOptions::default());
+
+            assert_eq!(
+                recursive_paths,
+                HashMap::from([(
+                    "Foo.h".to_string(),
+                    HashSet::from([vec![
+                        "Root.h".to_string(),
+                        "Foo.h".to_string(),
+                        "Foo.h".to_string()
+                    ]])
+                )])
+            );
+            assert_eq!(
+                crate::report::format_path(&recursive_paths["Foo.h"].iter().next().unwrap()[..]),
+                "Root.h->Foo.h[depth 1]->Foo.h (self)"
+            );
+        }
+
+        // Deep enough to overflow the stack of a recursive traversal
+        #[test]
+        fn traverses_a_5000_node_chain() {
+            const CHAIN_LENGTH: usize = 5000;
+
+            let fixture = Fixture::new(&["Engine/Source/Runtime/Core"]);
+            for i in 0..CHAIN_LENGTH {
+                fixture.write(
+                    &format!("{}/H{}.h", CORE_PUBLIC, i),
+                    &format!("#include \"H{}.h\"\n", (i + 1) % CHAIN_LENGTH),
+                );
+            }
+
+            let (node_tree, recursive_paths) = traverse_fixture(
+                &fixture,
+                &fixture.path(&format!("{}/H0.h", CORE_PUBLIC)),
+                &TraverseOptions::default(),
+            );
+
+            assert_eq!(node_tree.nodes.len(), CHAIN_LENGTH + 1);
+            assert_eq!(recursive_paths["H0.h"].len(), 1);
+            assert_eq!(
+                recursive_paths["H0.h"].iter().next().unwrap().len(),
+                CHAIN_LENGTH + 1
+            );
+        }
+
+        // Both sides of the diamond below `Top.h` lead back to it
+        #[test]
+        fn minimal_cycles_collapse_a_diamond_to_one_cycle() {
+            let fixture = Fixture::new(&["Engine/Source/Runtime/Core"]);
+            let entry_point =
+                fixture.write(&format!("{}/Entry.h", CORE_PUBLIC), "#include \"Top.h\"\n");
+            fixture.write(
+                &format!("{}/Top.h", CORE_PUBLIC),
+                "#include \"Left.h\"\n#include \"Right.h\"\n",
+            );
+            fixture.write(
+                &format!("{}/Left.h", CORE_PUBLIC),
+                "#include \"Bottom.h\"\n",
+            );
+            fixture.write(
+                &format!("{}/Right.h", CORE_PUBLIC),
+                "#include \"Bottom.h\"\n",
+            );
+            fixture.write(&format!("{}/Bottom.h", CORE_PUBLIC), "#include \"Top.h\"\n");
+
+            let paths = |minimal_cycles: bool| {
+                let options = TraverseOptions {
+                    minimal_cycles,
+                    ..TraverseOptions::default()
+                };
+
+                traverse_fixture(&fixture, &entry_point, &options)
+                    .1
+                    .into_values()
+                    .flatten()
+                    .collect::<HashSet<_>>()
+            };
+
+            let cycle = ["Top.h", "Left.h", "Bottom.h", "Top.h"].map(str::to_string);
+
+            assert_eq!(paths(true), HashSet::from([cycle.to_vec()]));
+            assert_eq!(
+                paths(false),
+                HashSet::from([[&["Entry.h".to_string()][..], &cycle].concat()])
+            );
+        }
+    }
+}
+
+pub mod project {
+    use std::{
+        cell::RefCell,
+        collections::{BTreeMap, HashMap, HashSet},
+        fmt::{Debug, Formatter},
+        fs::{self, File},
+        io::{BufRead, BufReader, Read, Write},
+        iter::FromIterator,
+        path::{Path, PathBuf},
+        rc::Rc,
+        time::Instant,
+    };
+
+    use anyhow::*;
+    use itertools::Itertools;
+    use petgraph::{
+        algo::tarjan_scc,
+        graph::{Graph, NodeIndex},
+    };
+
+    use crate::{
+        cmp_paths,
+        file_info::{
+            file_name_of, normalize_separators, open_with_retries, DefaultIncludeScanner, FileInfo,
+            FileType, IncludeScanner, PathPool, ScanOptions, DEFAULT_OPEN_RETRIES,
+        },
+        metrics::Metrics,
+    };
+
+    // Index into `Project::files`
+    pub type FileInfoId = usize;
+
+    pub const DEFAULT_CMAKE_LISTS: &str = "CMakeLists.txt";
+
+    // Catch-all module of the include paths read from a response file
+    pub const RESPONSE_FILE_MODULE: &str = "<response file>";
+    // Catch-all module of the files only found by searching the whole project
+    pub const PROJECT_ROOT_MODULE: &str = "<project root>";
+    // Followed by the directory of the entry point the module was made for
+    pub const AD_HOC_MODULE_PREFIX: &str = "<ad hoc>";
+
+    const UE_SUBFOLDERS: [&str; 4] = ["Public", "Private", "Classes", "Internal"];
+
+    pub const BUILD_CS_SUFFIX: &str = ".Build.cs";
+
+    // Folders the module paths of a cmake file start at, the first one matching wins. `*` stands
+    // for any single folder
+    pub const DEFAULT_SOURCE_ROOTS: [&str; 3] =
+        ["Engine/Plugins/*/Source", "Engine/Source", "Source"];
+
+    pub struct Project {
+        pub root_path: String,
+        pub modules: Vec<(String, Vec<String>)>,
+        pub files: Vec<Rc<RefCell<FileInfo>>>,
+        pub circular_dependency_paths: HashSet<Vec<String>>,
+        // Store paths as they were found instead of resolving symlinks, the same physical file
+        // reached through different symlinks then shows up as separate files
+        pub keep_symlink_paths: bool,
+        pub scan_options: ScanOptions,
+        // Modules tried first when resolving includes, in order
+        pub module_priority: Vec<String>,
+        // Friendly names for modules (alias -> module name), usable anywhere a module is named
+        pub module_aliases: HashMap<String, String>,
+        // Treat a `Foo.h`/`Foo.cpp` pair as one unit, see `merge_unit`
+        pub pair_units: bool,
+        // Also look for includes in the `Public`/`Private`/`Classes`/`Internal` subfolders of
+        // the modules, UE finds them there even when they aren't registered include paths
+        pub ue_subfolders: bool,
+        // As a last resort look for the include anywhere under `root_path`, slow on the first
+        // fallback since the whole project gets indexed. Turned on with
+        // `enable_whole_project_search`
+        search_whole_project: bool,
+        // Check every module for each include, not just until the first match, and record the
+        // includes more than one module has a file for in `ambiguous_includes`
+        pub diagnose_ambiguity: bool,
+        // Include string -> the candidate files, only filled with `diagnose_ambiguity`
+        pub ambiguous_includes: BTreeMap<String, Vec<(String, PathBuf)>>,
+        pub include_scanner: Box<dyn IncludeScanner>,
+        pub metrics: Metrics,
+        // `files` by absolute path for quick lookups
+        file_index: HashMap<Rc<str>, Rc<RefCell<FileInfo>>>,
+        // Headers that already got their source's includes
+        merged_units: HashSet<Rc<str>>,
+        // Files outside of it are flagged as external
+        canonical_root: PathBuf,
+        // `get_file` results by (include string, module of the including file), cleared whenever
+        // a module is added
+        resolved_includes: HashMap<(String, String), Option<Rc<RefCell<FileInfo>>>>,
+        // Every file under `root_path` by file name, built on the first whole project search
+        project_files: Option<HashMap<String, Vec<PathBuf>>>,
+        path_pool: PathPool,
+    }
+
+    impl Project {
+        // From the generated `CMakeLists.txt` if there is one, from the `*.Build.cs` files of the
+        // raw source tree otherwise
+        pub fn create(project_path: &str) -> Result<Self> {
+            Self::create_with_source_roots(project_path, None, &[])
+        }
+
+        // Whether `create` has anything to read the modules from, a `CMakeLists.txt` or at least
+        // one `*.Build.cs` file
+        pub fn has_module_definitions(project_path: &str) -> bool {
+            Self::has_cmake_lists(project_path)
+                || !Self::find_build_cs_files(project_path, true).is_empty()
+        }
+
+        fn has_cmake_lists(project_path: &str) -> bool {
+            Path::new(project_path).join(DEFAULT_CMAKE_LISTS).exists()
+        }
+
+        // `cmake_lists` is relative to the project root unless it's an absolute path
+        pub fn create_with_cmake_lists(project_path: &str, cmake_lists: &str) -> Result<Self> {
+            Self::from_cmake_lists(project_path, cmake_lists, &[], DEFAULT_OPEN_RETRIES)
+        }
+
+        // Module names of the cmake include paths are taken from the first of `source_roots`
+        // they contain, `DEFAULT_SOURCE_ROOTS` if empty
+        pub fn create_with_source_roots(
+            project_path: &str,
+            cmake_lists: Option<&str>,
+            source_roots: &[String],
+        ) -> Result<Self> {
+            Self::create_with_scan_options(
+                project_path,
+                cmake_lists,
+                source_roots,
+                &ScanOptions::default(),
+            )
+        }
+
+        // The cmake and `*.Build.cs` files are opened with the retries of `scan_options` too,
+        // they're on the same mount as the sources
+        pub fn create_with_scan_options(
+            project_path: &str,
+            cmake_lists: Option<&str>,
+            source_roots: &[String],
+            scan_options: &ScanOptions,
+        ) -> Result<Self> {
+            let open_retries = scan_options.open_retries;
+
+            let mut project = match cmake_lists {
+                Some(cmake_lists) => {
+                    Self::from_cmake_lists(project_path, cmake_lists, source_roots, open_retries)
+                }
+                None if Self::has_cmake_lists(project_path) => Self::from_cmake_lists(
+                    project_path,
+                    DEFAULT_CMAKE_LISTS,
+                    source_roots,
+                    open_retries,
+                ),
+                None => {
+                    info!(
+                        "No {} in {}, reading the modules from the *.Build.cs files",
+                        DEFAULT_CMAKE_LISTS, project_path
+                    );
+
+                    Self::from_build_cs(project_path, open_retries)
+                }
+            }?;
+            project.scan_options = scan_options.clone();
+
+            Ok(project)
+        }
+
+        #[instrument]
+        fn from_cmake_lists(
+            project_path: &str,
+            cmake_lists: &str,
+            source_roots: &[String],
+            open_retries: usize,
+        ) -> Result<Self> {
+            let source_roots: Vec<String> = match source_roots.is_empty() {
+                true => DEFAULT_SOURCE_ROOTS
+                    .iter()
+                    .map(|root| root.to_string())
+                    .collect(),
+                false => source_roots.to_vec(),
+            };
+
+            let cmake_lists_path = Path::new(project_path).join(cmake_lists);
+            let cmake_lists_file = open_with_retries(&cmake_lists_path, open_retries)
+                .with_context(|| format!("Couldn't open {}", cmake_lists_path.to_string_lossy()))?;
+
+            let mut modules: HashMap<String, HashSet<String>> = HashMap::new();
+
+            let cmake_lists_lines = BufReader::new(cmake_lists_file).lines();
+
+            // Variables usable in `include(${VAR}/...)`, filled by single line `set()` calls
+            let mut variables: HashMap<String, String> = HashMap::from_iter(
+                [
+                    "CMAKE_SOURCE_DIR",
+                    "CMAKE_CURRENT_SOURCE_DIR",
+                    "CMAKE_CURRENT_LIST_DIR",
+                ]
+                .map(|var_name| (var_name.to_string(), project_path.to_string())),
+            );
+
+            // Guards against cmake files including each other
+            let mut visited_cmake_files = HashSet::from([Self::cmake_file_key(&cmake_lists_path)]);
+
+            for (line_ind, cmake_lists_line) in cmake_lists_lines.enumerate() {
+                let cmake_lists_line = cmake_lists_line.with_context(|| {
+                    format!(
+                        "Couldn't read line {} of {}",
+                        line_ind + 1,
+                        cmake_lists_path.to_string_lossy()
+                    )
+                })?;
+
+                if let Some((var_name, value)) = Self::parse_set(&cmake_lists_line) {
+                    variables.insert(var_name, value);
+                    continue;
+                }
+
+                let stripped_cll = Self::strip_cmake_line(&cmake_lists_line);
+
+                if let Some(include_start) = stripped_cll.find("include(") {
+                    let include = stripped_cll[include_start + "include(".len()..]
+                        .trim_end_matches(')')
+                        .replace('\"', "");
+
+                    // Substituted first, the variable's value may be all that names the file
+                    let include = match Self::substitute_variables(&include, &variables) {
+                        std::result::Result::Ok(include) => include,
+                        Err(error) => {
+                            warn!("Skipping cmake include '{}': {}", include, error);
+                            continue;
+                        }
+                    };
+
+                    if !include.contains("includes") {
+                        continue;
+                    }
+
+                    Self::parse_cmake_include(
+                        &include,
+                        &source_roots,
+                        open_retries,
+                        &mut variables,
+                        &mut modules,
+                        &mut visited_cmake_files,
+                    )?;
+                }
+            }
+
+            // Sorted all the way down, the order decides which file an include resolves to
+            let mut res_modules: Vec<(String, Vec<String>)> = modules
+                .iter()
+                .map(|(module, include_paths)| {
+                    (
+                        module.clone(),
+                        include_paths
+                            .iter()
+                            .cloned()
+                            .sorted()
+                            .collect::<Vec<String>>(),
+                    )
+                })
+                .collect();
+            res_modules.sort_by(|(mod1, _inc1), (mod2, _inc2)| {
+                Ord::cmp(&mod1.len(), &mod2.len()).then_with(|| Ord::cmp(mod1, mod2))
+            });
+
+            // Every file would fail to find its module otherwise, with a much less helpful error
+            if res_modules.is_empty() {
+                bail!(
+                    "No modules were found in {}, check that it includes the cmake file listing \
+                     the include directories (an `include(...)` with \"includes\" in its path)",
+                    cmake_lists_path.to_string_lossy()
+                );
+            }
+
+            Ok(Self::with_modules(project_path, res_modules))
+        }
+
+        // Every `<Module>.Build.cs` under the project root is a module, its include paths are the
+        // conventional subfolders next to it plus the `PublicIncludePaths`/`PrivateIncludePaths`
+        // string literals. Relative ones are relative to the module's directory
+        pub fn from_build_cs(project_path: &str, open_retries: usize) -> Result<Self> {
+            let mut modules = vec![];
+
+            for build_cs_path in Self::find_build_cs_files(project_path, false) {
+                let module_dir = match build_cs_path.parent() {
+                    Some(module_dir) => module_dir,
+                    None => continue,
+                };
+                let module = match file_name_of(&build_cs_path.to_string_lossy())
+                    .strip_suffix(BUILD_CS_SUFFIX)
+                {
+                    Some(module) => module.to_string(),
+                    None => continue,
+                };
+
+                let mut contents = String::new();
+                let read = open_with_retries(&build_cs_path, open_retries)
+                    .and_then(|mut build_cs_file| build_cs_file.read_to_string(&mut contents));
+
+                if let Err(error) = read {
+                    warn!("Skipping {}: {}", build_cs_path.display(), error);
+                    continue;
+                }
+
+                let include_paths: Vec<String> = UE_SUBFOLDERS
+                    .iter()
+                    .map(|subfolder| module_dir.join(subfolder))
+                    .chain(Self::build_cs_include_paths(&contents, module_dir))
+                    .filter(|include_path| include_path.is_dir())
+                    // Files are matched to modules by their canonical paths
+                    .filter_map(|include_path| fs::canonicalize(include_path).ok())
+                    .map(|include_path| include_path.to_string_lossy().to_string())
+                    .unique()
+                    .collect();
+
+                if include_paths.is_empty() {
+                    warn!("Module {} has no include paths, skipping it", module);
+                    continue;
+                }
+
+                modules.push((module, include_paths));
+            }
+
+            if modules.is_empty() {
+                bail!(
+                    "Neither a {} nor any *{} file was found in {}",
+                    DEFAULT_CMAKE_LISTS,
+                    BUILD_CS_SUFFIX,
+                    project_path
+                );
+            }
+
+            modules.sort_by(|(mod1, _inc1), (mod2, _inc2)| {
+                Ord::cmp(&mod1.len(), &mod2.len()).then_with(|| Ord::cmp(mod1, mod2))
+            });
+
+            Ok(Self::with_modules(project_path, modules))
+        }
+
+        // Generated and build output folders hold copies, not modules. `first_only` stops at the
+        // first one found
+        fn find_build_cs_files(root_path: &str, first_only: bool) -> Vec<PathBuf> {
+            let mut build_cs_files = vec![];
+            let mut dirs = vec![PathBuf::from(root_path)];
+
+            while let Some(dir) = dirs.pop() {
+                let entries = match fs::read_dir(&dir) {
+                    std::result::Result::Ok(entries) => entries,
+                    Err(error) => {
+                        warn!("Couldn't read directory {}: {}", dir.display(), error);
+                        continue;
+                    }
+                };
+
+                for entry in entries.flatten() {
+                    let path = entry.path();
+                    let file_name = entry.file_name().to_string_lossy().to_string();
+
+                    if file_name.starts_with('.')
+                        || ["Intermediate", "Binaries", "Saved"].contains(&file_name.as_str())
+                    {
+                        continue;
+                    }
+
+                    if path.is_dir() {
+                        dirs.push(path);
+                    } else if file_name.ends_with(BUILD_CS_SUFFIX) {
+                        build_cs_files.push(path);
+
+                        if first_only {
+                            return build_cs_files;
+                        }
+                    }
+                }
+            }
+
+            build_cs_files.sort();
+            build_cs_files
+        }
+
+        // The string literals of every `PublicIncludePaths`/`PrivateIncludePaths` statement, up
+        // to its `;` so `AddRange(new string[] { ... })` over several lines works too
+        fn build_cs_include_paths(contents: &str, module_dir: &Path) -> Vec<PathBuf> {
+            let mut include_paths = vec![];
+
+            for key in ["PublicIncludePaths", "PrivateIncludePaths"] {
+                for (start, _) in contents.match_indices(key) {
+                    let statement = contents[start..].split(';').next().unwrap_or_default();
+
+                    for literal in statement.split('"').skip(1).step_by(2) {
+                        // `Path.Combine(ModuleDirectory, "Foo")` and plain `"Foo"` alike
+                        include_paths.push(module_dir.join(literal));
+                    }
+                }
+            }
+
+            include_paths
+        }
+
+        fn with_modules(project_path: &str, modules: Vec<(String, Vec<String>)>) -> Self {
+            Self {
+                root_path: project_path.to_string(),
+                modules,
+                files: vec![],
+                circular_dependency_paths: HashSet::new(),
+                keep_symlink_paths: false,
+                scan_options: ScanOptions::default(),
+                module_priority: vec![],
+                module_aliases: HashMap::new(),
+                pair_units: false,
+                ue_subfolders: false,
+                search_whole_project: false,
+                diagnose_ambiguity: false,
+                ambiguous_includes: BTreeMap::new(),
+                include_scanner: Box::new(DefaultIncludeScanner),
+                metrics: Metrics::default(),
+                file_index: HashMap::new(),
+                merged_units: HashSet::new(),
+                resolved_includes: HashMap::new(),
+                project_files: None,
+                canonical_root: fs::canonicalize(project_path)
+                    .unwrap_or_else(|_| PathBuf::from(project_path)),
+                path_pool: PathPool::default(),
+            }
+        }
+
+        pub fn create_file_info(&mut self, abs_path: &str) -> Result<Rc<RefCell<FileInfo>>> {
+            let abs_path = self.resolve_path(abs_path)?;
+            let parsing_start = Instant::now();
+            let file_info = FileInfo::create(
+                &abs_path,
+                &self.modules,
+                &self.scan_options,
+                &*self.include_scanner,
+                &mut self.path_pool,
+            );
+            self.metrics.file_parsing += parsing_start.elapsed();
+
+            let file_info = file_info?;
+            self.metrics.files_parsed += 1;
+
+            self.files.push(file_info.clone());
+            self.file_index
+                .insert((*file_info).borrow().abs_path.clone(), file_info.clone());
+
+            Ok(file_info)
+        }
+
+        pub fn create_leaf_file_info(
+            &mut self,
+            abs_path: &str,
+            reason: &str,
+        ) -> Rc<RefCell<FileInfo>> {
+            warn!(
+                "Couldn't parse {}, keeping it as a leaf: {}",
+                abs_path, reason
+            );
+
+            let file_info = FileInfo::leaf(
+                abs_path,
+                &self.modules,
+                reason,
+                &self.scan_options,
+                &mut self.path_pool,
+            );
+
+            self.files.push(file_info.clone());
+            self.file_index
+                .insert((*file_info).borrow().abs_path.clone(), file_info.clone());
+
+            file_info
+        }
+
+        // File info to start an analysis from, reuses the cached one if the file was seen already
+        pub fn entry_file_info(&mut self, abs_path: &str) -> Result<Rc<RefCell<FileInfo>>> {
+            let file_info = self.cached_file_info(abs_path)?;
+
+            self.merge_unit(file_info)
+        }
+
+        // With `pair_units` a source is folded into its header (same name, `Public`/`Private`
+        // swapped or the same directory): the header gets the source's includes on top of its
+        // own and anything resolving to the source resolves to the header instead
+        fn merge_unit(
+            &mut self,
+            file_info: Rc<RefCell<FileInfo>>,
+        ) -> Result<Rc<RefCell<FileInfo>>> {
+            if !self.pair_units {
+                return Ok(file_info);
+            }
+
+            let (abs_path, file_type) = {
+                let file_info = (*file_info).borrow();
+                (file_info.abs_path.clone(), file_info.file_type)
+            };
+
+            match file_type {
+                FileType::Source => match self.unit_counterpart(&abs_path, &["h", "hpp"]) {
+                    Some(header) => self.merge_unit(header),
+                    None => Ok(file_info),
+                },
+                FileType::Header => {
+                    if self.merged_units.insert(abs_path.clone()) {
+                        if let Some(source) = self.unit_counterpart(&abs_path, &["cpp", "c"]) {
+                            let header_name = (*file_info).borrow().file_name.clone();
+
+                            // The source includes its own header, which would be a self include
+                            let source_includes = (*source)
+                                .borrow()
+                                .includes
+                                .iter()
+                                .filter(|include| {
+                                    Path::new(&include.path)
+                                        .file_name()
+                                        .is_none_or(|name| name.to_str() != Some(&*header_name))
+                                })
+                                .cloned()
+                                .collect::<Vec<_>>();
+
+                            (*file_info).borrow_mut().includes.extend(source_includes);
+                        }
+                    }
+
+                    Ok(file_info)
+                }
+                FileType::Inline | FileType::Unsupported => Ok(file_info),
+            }
+        }
+
+        fn unit_counterpart(
+            &mut self,
+            abs_path: &str,
+            extensions: &[&str],
+        ) -> Option<Rc<RefCell<FileInfo>>> {
+            let path = Path::new(abs_path);
+            let (dir, stem) = (path.parent()?, path.file_stem()?.to_str()?);
+
+            let swapped_dir: PathBuf = dir
+                .components()
+                .map(|component| match component.as_os_str().to_str() {
+                    Some("Public") => "Private".as_ref(),
+                    Some("Private") => "Public".as_ref(),
+                    _ => component.as_os_str(),
+                })
+                .collect();
+
+            for dir in [dir.to_path_buf(), swapped_dir] {
+                for extension in extensions {
+                    let counterpart = dir.join(format!("{}.{}", stem, extension));
+
+                    if counterpart.exists() {
+                        return self.cached_file_info(&counterpart.to_string_lossy()).ok();
+                    }
+                }
+            }
+
+            None
+        }
+
+        // Parses and caches a file without a traversal, e.g. to pre-seed the cache or to inspect
+        // a single file. A file that's already known is returned as it is
+        pub fn add_file(&mut self, abs_path: &str) -> Result<Rc<RefCell<FileInfo>>> {
+            self.cached_file_info(abs_path)
+        }
+
+        fn cached_file_info(&mut self, abs_path: &str) -> Result<Rc<RefCell<FileInfo>>> {
+            let abs_path = self.resolve_path(abs_path)?;
+
+            match self.file_index.get(abs_path.as_str()) {
+                Some(file_info) => Ok(file_info.clone()),
+                None => self.create_file_info(&abs_path),
+            }
+        }
+
+        // Falls back to searching the whole project for includes under no include path. The root
+        // is registered as the last module, the found files couldn't be parsed without a module
+        pub fn enable_whole_project_search(&mut self) {
+            if self.search_whole_project {
+                return;
+            }
+
+            self.search_whole_project = true;
+            self.modules.push((
+                PROJECT_ROOT_MODULE.to_string(),
+                vec![self.canonical_root.to_string_lossy().to_string()],
+            ));
+            self.resolved_includes.clear();
+        }
+
+        // Makes a file under no include path analyzable as an entry point (a scratch header), by
+        // registering its own directory as a module of its own. Nothing happens for a file that
+        // already has a module
+        pub fn add_ad_hoc_module(&mut self, abs_path: &str) -> Result<()> {
+            let abs_path = self.resolve_path(abs_path)?;
+
+            if FileInfo::find_module(&abs_path, &self.modules).is_some() {
+                return Ok(());
+            }
+
+            let dir = match Path::new(&abs_path).parent() {
+                Some(dir) => dir.to_string_lossy().to_string(),
+                None => bail!("{} has no parent directory", abs_path),
+            };
+
+            info!(
+                "{} isn't in any module, adding an ad hoc module for {}",
+                abs_path, dir
+            );
+
+            self.modules
+                .push((format!("{} {}", AD_HOC_MODULE_PREFIX, dir), vec![dir]));
+            self.resolved_includes.clear();
+
+            Ok(())
+        }
+
+        // Registers the `-I`/`-isystem` paths of a compiler response file as one extra module,
+        // tried after all the others. Relative paths are relative to the project root
+        pub fn add_response_file(&mut self, response_file_path: &str) -> Result<()> {
+            let response_file =
+                fs::read_to_string(Path::new(response_file_path)).with_context(|| {
+                    format!("Couldn't read the response file: {}", response_file_path)
+                })?;
+
+            let mut include_paths = vec![];
+            let mut tokens = Self::response_file_tokens(&response_file).into_iter();
+
+            while let Some(token) = tokens.next() {
+                let include_path = match ["-isystem", "-I"]
+                    .iter()
+                    .find_map(|flag| token.strip_prefix(flag))
+                {
+                    Some("") => tokens.next(),
+                    Some(include_path) => Some(include_path.to_string()),
+                    None => None,
+                };
+
+                if let Some(include_path) = include_path {
+                    let include_path = Path::new(&self.root_path)
+                        .join(include_path)
+                        .to_string_lossy()
+                        .to_string();
+
+                    // Resolved like the files are, so files can be matched to them
+                    include_paths.push(self.resolve_path(&include_path).unwrap_or(include_path));
+                }
+            }
+
+            info!(
+                "{} include paths in the response file {}",
+                include_paths.len(),
+                response_file_path
+            );
+
+            if !include_paths.is_empty() {
+                self.modules
+                    .push((RESPONSE_FILE_MODULE.to_string(), include_paths));
+                self.resolved_includes.clear();
+            }
+
+            Ok(())
+        }
+
+        // Whitespace separated, double quotes group a token and are removed
+        fn response_file_tokens(response_file: &str) -> Vec<String> {
+            let mut tokens = vec![];
+            let mut token = String::new();
+            let mut quoted = false;
+
+            for c in response_file.chars() {
+                match c {
+                    '"' => quoted = !quoted,
+                    c if c.is_whitespace() && !quoted => {
+                        if !token.is_empty() {
+                            tokens.push(std::mem::take(&mut token));
+                        }
+                    }
+                    c => token.push(c),
+                }
+            }
+
+            if !token.is_empty() {
+                tokens.push(token);
+            }
+
+            tokens
+        }
+
+        // Parses every supported file under the include paths of all modules (or only the given
+        // one), so the whole project ends up in `files` and not just what's reachable from an
+        // entry point
+        pub fn add_module_files(&mut self, module: Option<&str>) -> Result<()> {
+            let module = module.map(|module| self.module_name(module).to_string());
+            let module = module.as_deref();
+
+            let include_paths: Vec<String> = self
+                .modules
+                .iter()
+                .filter(|(modl, _include_paths)| module.is_none_or(|module| modl == module))
+                .flat_map(|(_modl, include_paths)| include_paths.clone())
+                .collect();
+
+            if let Some(module) = module {
+                if include_paths.is_empty() {
+                    bail!("Module '{}' wasn't found in the project", module);
+                }
+            }
+
+            let mut dirs: Vec<PathBuf> = include_paths.iter().map(PathBuf::from).collect();
+
+            while let Some(dir) = dirs.pop() {
+                let entries = match fs::read_dir(&dir) {
+                    std::result::Result::Ok(entries) => entries,
+                    Err(error) => {
+                        warn!("Couldn't read directory {}: {}", dir.display(), error);
+                        continue;
+                    }
+                };
+
+                for entry in entries.flatten() {
+                    let path = entry.path();
+
+                    if path.is_dir() {
+                        dirs.push(path);
+                        continue;
+                    }
+
+                    let is_supported = path
+                        .extension()
+                        .and_then(|extension| extension.to_str())
+                        .and_then(|extension| self.scan_options.file_type(extension))
+                        .is_some();
+
+                    if is_supported {
+                        if let Err(error) = self.cached_file_info(&path.to_string_lossy()) {
+                            warn!("Skipping {}: {}", path.display(), error);
+                        }
+                    }
+                }
+            }
+
+            Ok(())
+        }
+
+        // Strongly connected components of the include graph of everything in `files` that
+        // contain a cycle (more than one file, or a file including itself). Unlike the entry
+        // point traversal this finds every circular dependency group at once
+        pub fn cycle_clusters(&mut self) -> Vec<Vec<Rc<RefCell<FileInfo>>>> {
+            let graph = self.include_graph();
+
+            tarjan_scc(&graph)
+                .into_iter()
+                .filter(|component| {
+                    component.len() > 1 || graph.find_edge(component[0], component[0]).is_some()
+                })
+                .map(|component| {
+                    component
+                        .into_iter()
+                        .map(|node| self.file_info_of(&graph, node))
+                        .sorted_by_key(|file_info| {
+                            let file_info = (*file_info).borrow();
+                            (file_info.file_type, file_info.file_name.clone())
+                        })
+                        .collect()
+                })
+                .collect()
+        }
+
+        // The module an alias stands for, anything else is taken as a module name already
+        pub fn module_name<'a>(&'a self, name: &'a str) -> &'a str {
+            self.module_aliases
+                .get(name)
+                .map_or(name, |module| module.as_str())
+        }
+
+        // Modules found in the CMake files with their include paths, shortest module first
+        pub fn modules(&self) -> &[(String, Vec<String>)] {
+            &self.modules
+        }
+
+        // One module per line followed by its (sorted) include paths, to check what the CMake
+        // parsing found
+        pub fn write_modules(&self, file: &mut impl Write) -> Result<()> {
+            for (module, include_paths) in self.modules.iter() {
+                file.write_all(format!("{}\n", module).as_bytes())?;
+
+                for include_path in include_paths.iter().sorted() {
+                    file.write_all(format!("\t{}\n", include_path).as_bytes())?;
+                }
+            }
+
+            Ok(())
+        }
+
+        pub fn dump_modules(&self, path: &str) -> Result<()> {
+            let mut file = File::create(Path::new(path))
+                .with_context(|| format!("Couldn't create {}", path))?;
+
+            self.write_modules(&mut file)
+        }
+
+        // Every acyclic include path from the entry point to the target (matched by absolute
+        // path or file name), answers "why does this file end up being included". The number
+        // of paths can grow exponentially on dense include graphs
+        pub fn paths_to(&mut self, entry_point: &str, target: &str) -> Result<Vec<Vec<String>>> {
+            let entry_file_info = self.entry_file_info(entry_point)?;
+
+            let mut paths = vec![];
+            let mut stack = vec![vec![entry_file_info]];
+
+            while let Some(path) = stack.pop() {
+                let (is_target, includes, module) = {
+                    let last = (*path.last().unwrap()).borrow();
+
+                    (
+                        &*last.abs_path == target || &*last.file_name == target,
+                        last.includes.clone(),
+                        last.module.clone(),
+                    )
+                };
+
+                if is_target {
+                    paths.push(
+                        path.iter()
+                            .map(|file_info| (*file_info).borrow().file_name.to_string())
+                            .collect(),
+                    );
+                    continue;
+                }
+
+                for include in includes.iter() {
+                    if let std::result::Result::Ok(include_file_info) =
+                        self.get_file(&include.path, &module)
+                    {
+                        // Files already on the path would make it cyclic
+                        if path
+                            .iter()
+                            .any(|file_info| Rc::ptr_eq(file_info, &include_file_info))
+                        {
+                            continue;
+                        }
+
+                        let mut next_path = path.clone();
+                        next_path.push(include_file_info);
+
+                        stack.push(next_path);
+                    }
+                }
+            }
+
+            paths.sort_by(|path1: &Vec<String>, path2| cmp_paths(path1, path2));
+
+            Ok(paths)
+        }
+
+        // Graph of every resolvable include edge starting from the files that are already known
+        // (e.g. entry points), newly resolved files get their includes resolved as well. Node
+        // weights index into `files`, `file_info_of` maps graph nodes back to their `FileInfo`
+        pub fn include_graph(&mut self) -> Graph<FileInfoId, ()> {
+            let mut graph = Graph::new();
+            let mut ids = HashMap::new();
+
+            self.add_graph_nodes(&mut graph, &mut ids);
+
+            let mut file_id = 0;
+            while file_id < self.files.len() {
+                let (includes, module) = {
+                    let file_info = (*self.files[file_id]).borrow();
+                    (file_info.includes.clone(), file_info.module.clone())
+                };
+
+                for include in includes.iter() {
+                    if let std::result::Result::Ok(include_file_info) =
+                        self.get_file(&include.path, &module)
+                    {
+                        // Resolving might have found new files
+                        self.add_graph_nodes(&mut graph, &mut ids);
+
+                        let include_id = ids[&(*include_file_info).borrow().abs_path];
+
+                        graph.update_edge(NodeIndex::new(file_id), NodeIndex::new(include_id), ());
+                    }
+                }
+
+                file_id += 1;
+            }
+
+            graph
+        }
+
+        fn add_graph_nodes(
+            &self,
+            graph: &mut Graph<FileInfoId, ()>,
+            ids: &mut HashMap<Rc<str>, FileInfoId>,
+        ) {
+            for file_id in graph.node_count()..self.files.len() {
+                graph.add_node(file_id);
+                ids.insert((*self.files[file_id]).borrow().abs_path.clone(), file_id);
+            }
+        }
+
+        pub fn file_info_of(
+            &self,
+            graph: &Graph<FileInfoId, ()>,
+            node: NodeIndex,
+        ) -> Rc<RefCell<FileInfo>> {
+            self.files[graph[node]].clone()
+        }
+
+        // Resolutions are cached by include string and module, common headers are included from
+        // thousands of files and every attempt costs a few filesystem lookups
+        pub fn get_file(
+            &mut self,
+            partial_path: &str,
+            entry_module: &str,
+        ) -> Result<Rc<RefCell<FileInfo>>> {
+            let key = (partial_path.to_string(), entry_module.to_string());
+
+            let file = match self.resolved_includes.get(&key) {
+                Some(file) => file.clone(),
+                None => {
+                    let file = self.resolve_file(partial_path, entry_module).ok();
+                    self.resolved_includes.insert(key, file.clone());
+
+                    file
+                }
+            };
+
+            match file {
+                Some(file) => Ok(file),
+                None => bail!("Couldn't get the file"),
+            }
+        }
+
+        fn resolve_file(
+            &mut self,
+            partial_path: &str,
+            entry_module: &str,
+        ) -> Result<Rc<RefCell<FileInfo>>> {
+            if self.diagnose_ambiguity {
+                self.check_ambiguity(partial_path);
+            }
+
+            // Modules ambiguous includes should resolve to are tried before anything else
+            for priority_module in self.module_priority.clone() {
+                let modl = self
+                    .modules
+                    .iter()
+                    .find(|(modl, _include_paths)| modl == self.module_name(&priority_module))
+                    .cloned();
+
+                if let Some(modl) = modl {
+                    if let std::result::Result::Ok(file) =
+                        self.get_file_in_module(modl, partial_path)
+                    {
+                        return Ok(file);
+                    }
+                }
+            }
+
+            // Check if root module actually exists
+            let mut root_module = None;
+
+            for modl in self.modules.clone() {
+                if modl.0 == entry_module {
+                    root_module = Some(modl);
+                    break;
+                }
+            }
+
+            // If it does
+            if root_module.is_some() {
+                let modl = root_module.clone().unwrap();
+
+                if let std::result::Result::Ok(file) = self.get_file_in_module(modl, partial_path) {
+                    return Ok(file);
+                }
+            }
+
+            let other_modules: Vec<(String, Vec<String>)> = if let Some(root_mod) = root_module {
+                self.modules
+                    .iter()
+                    .filter(|(modl, _include_paths)| modl != &root_mod.0)
+                    .cloned()
+                    .collect()
+            } else {
+                self.modules.clone()
+            };
+
+            for module in other_modules {
+                if self
+                    .module_priority
+                    .iter()
+                    .any(|priority_module| self.module_name(priority_module) == module.0)
+                {
+                    continue;
+                }
+
+                if let std::result::Result::Ok(file) = self.get_file_in_module(module, partial_path)
+                {
+                    return Ok(file);
+                }
+            }
+
+            if self.search_whole_project {
+                if let Some(path_to_file) = self.find_in_project(partial_path) {
+                    info!(
+                        "'{}' isn't under any include path, falling back to {}",
+                        partial_path,
+                        path_to_file.display()
+                    );
+
+                    return self.file_at(&path_to_file);
+                }
+            }
+
+            bail!("Couldn't get the file");
+        }
+
+        // First file under the project root whose path ends with the include's path
+        fn find_in_project(&mut self, partial_path: &str) -> Option<PathBuf> {
+            let components = partial_path
+                .split(['/', '\\'])
+                .filter(|component| !component.is_empty() && *component != ".")
+                .collect::<Vec<_>>();
+            let file_name = components.last()?;
+
+            let project_files = match &self.project_files {
+                Some(project_files) => project_files,
+                None => self
+                    .project_files
+                    .insert(Self::index_files(&self.root_path)),
+            };
+
+            project_files
+                .get(*file_name)?
+                .iter()
+                .find(|path| path.ends_with(components.iter().collect::<PathBuf>()))
+                .cloned()
+        }
+
+        fn index_files(root_path: &str) -> HashMap<String, Vec<PathBuf>> {
+            let mut project_files: HashMap<String, Vec<PathBuf>> = HashMap::new();
+            let mut dirs = vec![PathBuf::from(root_path)];
+
+            while let Some(dir) = dirs.pop() {
+                let entries = match fs::read_dir(&dir) {
+                    std::result::Result::Ok(entries) => entries,
+                    Err(error) => {
+                        warn!("Couldn't read directory {}: {}", dir.display(), error);
+                        continue;
+                    }
+                };
+
+                for entry in entries.flatten() {
+                    let path = entry.path();
+                    let file_name = entry.file_name().to_string_lossy().to_string();
+
+                    // `.git` and the like
+                    if file_name.starts_with('.') {
+                        continue;
+                    }
+
+                    if path.is_dir() {
+                        dirs.push(path);
+                    } else {
+                        project_files.entry(file_name).or_default().push(path);
+                    }
+                }
+            }
+
+            // Directory listing order isn't stable, the fallback should be
+            for paths in project_files.values_mut() {
+                paths.sort();
+            }
+
+            project_files
+        }
+
+        // Which file the include resolves to depends on the module order and the including
+        // module when several modules have one, so the graph is only as deterministic as that
+        // order. Files found through several modules are only counted once
+        fn check_ambiguity(&mut self, partial_path: &str) {
+            if self.ambiguous_includes.contains_key(partial_path) {
+                return;
+            }
+
+            let mut candidates: Vec<(String, PathBuf)> = vec![];
+
+            for modl in self.modules.iter() {
+                if let Some(path_to_file) = self.path_in_module(modl, partial_path) {
+                    let canonical_path =
+                        fs::canonicalize(&path_to_file).unwrap_or_else(|_| path_to_file.clone());
+
+                    if !candidates
+                        .iter()
+                        .any(|(_modl, path)| path == &canonical_path)
+                    {
+                        candidates.push((modl.0.clone(), canonical_path));
+                    }
+                }
+            }
+
+            if candidates.len() > 1 {
+                warn!(
+                    "'{}' is ambiguous, it resolves in {} modules: {}",
+                    partial_path,
+                    candidates.len(),
+                    candidates.iter().map(|(modl, _path)| modl).join(", ")
+                );
+
+                self.ambiguous_includes
+                    .insert(partial_path.to_string(), candidates);
+            }
+        }
+
+        fn get_file_in_module(
+            &mut self,
+            modl: (String, Vec<String>),
+            partial_path: &str,
+        ) -> Result<Rc<RefCell<FileInfo>>> {
+            match self.path_in_module(&modl, partial_path) {
+                Some(path_to_file) => self.file_at(&path_to_file),
+                None => bail!("Couldn't get the file in module"),
+            }
+        }
+
+        fn path_in_module(
+            &self,
+            modl: &(String, Vec<String>),
+            partial_path: &str,
+        ) -> Option<PathBuf> {
+            let mut search_paths: Vec<PathBuf> = modl.1.iter().map(PathBuf::from).collect();
+
+            if self.ue_subfolders {
+                search_paths.extend(Self::module_subfolders(&modl.1));
+            }
+
+            // Check if any of the paths inside of the module are viable for the file we're looking
+            // for
+            for include_path in search_paths.iter() {
+                // Include strings are always '/' separated no matter the OS, so the partial path
+                // is joined onto the (OS native) include path component by component
+                let mut path_to_file = include_path.clone();
+                for component in partial_path
+                    .split(['/', '\\'])
+                    .filter(|component| !component.is_empty() && *component != ".")
+                {
+                    path_to_file.push(component);
+                }
+
+                // If path exists on the computer
+                if path_to_file.exists() {
+                    return Some(path_to_file);
+                }
+            }
+
+            None
+        }
+
+        // File info of an include target known to exist
+        fn file_at(&mut self, path_to_file: &Path) -> Result<Rc<RefCell<FileInfo>>> {
+            let path_to_file = self.resolve_path(&path_to_file.to_string_lossy())?;
+
+            // Return cached file info if it exists, otherwise create, cache and return it
+            let file_info = match self.cached_file_info(&path_to_file) {
+                std::result::Result::Ok(file_info) => file_info,
+                Err(error) => self.create_leaf_file_info(&path_to_file, &error.to_string()),
+            };
+
+            // Compared canonicalized, symlinks could point either way
+            let canonical_path =
+                fs::canonicalize(&path_to_file).unwrap_or_else(|_| PathBuf::from(&path_to_file));
+            if !canonical_path.starts_with(&self.canonical_root) {
+                (*file_info).borrow_mut().external = true;
+            }
+
+            self.merge_unit(file_info)
+        }
+
+        // From the last occurrence of the first matching source root up to the module's
+        // `Public`/`Private`/... folder, e.g. `Engine/Plugins/Foo/Source/Bar`. The folder above
+        // those subfolders if no root matches
+        fn module_of_include_path(include_path: &str, source_roots: &[String]) -> String {
+            let normalized_path = normalize_separators(include_path);
+            let folders: Vec<&str> = normalized_path
+                .split('/')
+                .filter(|folder| !folder.is_empty())
+                .collect();
+
+            let module_end = |start: usize| {
+                folders[start..]
+                    .iter()
+                    .position(|folder| UE_SUBFOLDERS.contains(folder))
+                    .map_or(folders.len(), |ind| start + ind)
+            };
+
+            for source_root in source_roots.iter() {
+                let root_folders: Vec<&str> = source_root
+                    .split(['/', '\\'])
+                    .filter(|folder| !folder.is_empty())
+                    .collect();
+
+                if root_folders.is_empty() || root_folders.len() > folders.len() {
+                    continue;
+                }
+
+                let root_start = (0..=folders.len() - root_folders.len())
+                    .rev()
+                    .find(|&start| {
+                        root_folders
+                            .iter()
+                            .zip(&folders[start..])
+                            .all(|(root_folder, folder)| {
+                                *root_folder == "*" || root_folder == folder
+                            })
+                    });
+
+                if let Some(root_start) = root_start {
+                    let end = module_end(root_start + root_folders.len());
+
+                    if end > root_start + root_folders.len() {
+                        return folders[root_start..end].join("/");
+                    }
+                }
+            }
+
+            match module_end(0) {
+                0 => normalized_path,
+                end => folders[end - 1].to_string(),
+            }
+        }
+
+        // The conventional UE subfolders next to the registered include paths, which aren't
+        // always registered themselves
+        fn module_subfolders(include_paths: &[String]) -> Vec<PathBuf> {
+            let mut subfolders = vec![];
+
+            for include_path in include_paths.iter() {
+                let include_path = Path::new(include_path);
+
+                let module_root = match include_path.file_name().and_then(|name| name.to_str()) {
+                    Some(name) if UE_SUBFOLDERS.contains(&name) => include_path.parent(),
+                    _ => Some(include_path),
+                };
+
+                for subfolder in module_root.into_iter().flat_map(|module_root| {
+                    UE_SUBFOLDERS
+                        .iter()
+                        .map(move |subfolder| module_root.join(subfolder))
+                }) {
+                    let registered = include_paths
+                        .iter()
+                        .any(|include_path| Path::new(include_path) == subfolder);
+
+                    if !registered && !subfolders.contains(&subfolder) {
+                        subfolders.push(subfolder);
+                    }
+                }
+            }
+
+            subfolders
+        }
+
+        // Collects the include folders listed in an included cmake file, following its own
+        // `include(...)`s however deep they go
+        fn parse_cmake_include(
+            include: &str,
+            source_roots: &[String],
+            open_retries: usize,
+            variables: &mut HashMap<String, String>,
+            modules: &mut HashMap<String, HashSet<String>>,
+            visited_cmake_files: &mut HashSet<PathBuf>,
+        ) -> Result<()> {
+            if !visited_cmake_files.insert(Self::cmake_file_key(Path::new(include))) {
+                warn!("Cmake file {} was already included, skipping it", include);
+                return Ok(());
+            }
+
+            let include_cmake_file = open_with_retries(Path::new(include), open_retries)
+                .with_context(|| format!("Couldn't open the cmake include {}", include))?;
+
+            let include_cmake_file_lines = BufReader::new(include_cmake_file).lines();
+
+            for (line_ind, include_cmake_file_line) in include_cmake_file_lines.enumerate() {
+                let include_cmake_file_line = include_cmake_file_line.with_context(|| {
+                    format!("Couldn't read line {} of {}", line_ind + 1, include)
+                })?;
+
+                if let Some((var_name, value)) = Self::parse_set(&include_cmake_file_line) {
+                    variables.insert(var_name, value);
+                    continue;
+                }
+
+                let stripped_ifl = Self::strip_cmake_line(&include_cmake_file_line);
+
+                if let Some(include_start) = stripped_ifl.find("include(") {
+                    let nested_include = stripped_ifl[include_start + "include(".len()..]
+                        .trim_end_matches(')')
+                        .replace('\"', "");
+
+                    match Self::substitute_variables(&nested_include, variables) {
+                        std::result::Result::Ok(nested_include) => Self::parse_cmake_include(
+                            &nested_include,
+                            source_roots,
+                            open_retries,
+                            variables,
+                            modules,
+                            visited_cmake_files,
+                        )?,
+                        Err(error) => {
+                            warn!("Skipping cmake include '{}': {}", nested_include, error)
+                        }
+                    }
+
+                    continue;
+                }
+
+                if stripped_ifl.contains('\"') {
+                    let inc_folder = stripped_ifl.replace('\"', "");
+
+                    if inc_folder.contains("Intermediate") {
+                        continue;
+                    }
+
+                    let module = Self::module_of_include_path(&inc_folder, source_roots);
+
+                    if modules.contains_key(module.clone().as_str()) {
+                        modules
+                            .get_mut(module.clone().as_str())
+                            .unwrap()
+                            .insert(inc_folder);
+                    } else {
+                        modules.insert(module.clone(), HashSet::from_iter([inc_folder]));
+                    }
+                }
+            }
+
+            Ok(())
+        }
+
+        // Without any whitespace, tabs and the `\r` of CRLF files included, so both parse loops see
+        // the lines the same way
+        fn strip_cmake_line(line: &str) -> String {
+            line.chars()
+                .filter(|character| !character.is_whitespace())
+                .collect()
+        }
+
+        // The same cmake file can be reached through differently written paths
+        fn cmake_file_key(path: &Path) -> PathBuf {
+            fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+        }
+
+        // `set(VAR value)` on a single line, multi-line lists are not supported
+        fn parse_set(line: &str) -> Option<(String, String)> {
+            let line = line.trim();
+
+            if !line.to_lowercase().starts_with("set(") || !line.ends_with(')') {
+                return None;
+            }
+
+            let mut arguments = line["set(".len()..line.len() - 1].split_whitespace();
+
+            let var_name = arguments.next()?.to_string();
+            let value = arguments.collect::<Vec<_>>().join(" ").replace('"', "");
+
+            Some((var_name, value))
+        }
+
+        fn substitute_variables(
+            value: &str,
+            variables: &HashMap<String, String>,
+        ) -> Result<String> {
+            let mut result = value.to_string();
+
+            while let Some(var_start) = result.find("${") {
+                let var_end = match result[var_start..].find('}') {
+                    Some(var_end) => var_start + var_end,
+                    None => bail!("Unterminated variable reference"),
+                };
+
+                let var_name = &result[var_start + 2..var_end];
+
+                let var_value = match variables.get(var_name) {
+                    Some(var_value) => var_value,
+                    None => bail!("Couldn't resolve variable '{}'", var_name),
+                };
+
+                result = format!(
+                    "{}{}{}",
+                    &result[..var_start],
+                    var_value,
+                    &result[var_end + 1..]
+                );
+            }
+
+            Ok(result)
+        }
+
+        fn resolve_path(&self, path: &str) -> Result<String> {
+            if self.keep_symlink_paths {
+                return Ok(path.to_string());
+            }
+
+            match fs::canonicalize(path)?.to_str() {
+                Some(canonical_path) => Ok(canonical_path.to_string()),
+                None => bail!("Canonical path of {} is not valid UTF-8", path),
+            }
+        }
+    }
+
+    impl Debug for Project {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            writeln!(f, "Project [")?;
+            writeln!(f, "\tRoot Path: {}", self.root_path)?;
+            writeln!(f, "\tModules: [")?;
+            for module in self.modules.iter() {
+                writeln!(f, "\t\t(")?;
+                writeln!(f, "\t\t\tModule: {}", module.0)?;
+                writeln!(f, "\t\t\tInclude Paths: [")?;
+                for include_path in module.1.iter() {
+                    writeln!(f, "\t\t\t\t{},", include_path)?;
+                }
+                writeln!(f, "\t\t\t]")?;
+                writeln!(f, "\t\t)")?;
+            }
+            writeln!(f, "\t]")?;
+            writeln!(f, "\tfiles: {:?}", self.files)?;
+            writeln!(f, "\tKeep Symlink Paths: {}", self.keep_symlink_paths)?;
+            writeln!(f, "\tScan Options: {:?}", self.scan_options)?;
+            writeln!(f, "\tModule Priority: {:?}", self.module_priority)?;
+            writeln!(f, "\tModule Aliases: {:?}", self.module_aliases)?;
+            writeln!(f, "\tPair Units: {}", self.pair_units)?;
+            writeln!(f, "\tUE Subfolders: {}", self.ue_subfolders)?;
+            writeln!(f, "\tSearch Whole Project: {}", self.search_whole_project)?;
+            writeln!(f, "\tDiagnose Ambiguity: {}", self.diagnose_ambiguity)?;
+            writeln!(f, "\tAmbiguous Includes: {:?}", self.ambiguous_includes)?;
+            writeln!(f, "\tInterned Paths: {}", self.path_pool.len())?;
+            writeln!(f, "\tMetrics: {:?}", self.metrics)?;
+            writeln!(f, "]")
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::test_fixture::Fixture;
+
+        // `Linked/Public` is a symlink to `Core/Public`
+        #[cfg(unix)]
+        #[test]
+        fn symlinked_include_root_resolves_to_the_same_file() {
+            let fixture =
+                Fixture::new(&["Engine/Source/Runtime/Core", "Engine/Source/Runtime/Linked"]);
+            fixture.write("Engine/Source/Runtime/Core/Public/Foo.h", "");
+            fs::remove_dir(fixture.path("Engine/Source/Runtime/Linked/Public")).unwrap();
+            std::os::unix::fs::symlink(
+                fixture.path("Engine/Source/Runtime/Core/Public"),
+                fixture.path("Engine/Source/Runtime/Linked/Public"),
+            )
+            .unwrap();
+
+            let resolve_both = |project: &mut Project| {
+                (
+                    project
+                        .get_file("Foo.h", "Engine/Source/Runtime/Core")
+                        .unwrap(),
+                    project
+                        .get_file("Foo.h", "Engine/Source/Runtime/Linked")
+                        .unwrap(),
+                )
+            };
+
+            let mut project = Project::create(fixture.root()).unwrap();
+            let (direct, linked) = resolve_both(&mut project);
+            assert!(Rc::ptr_eq(&direct, &linked));
+
+            let mut project = Project::create(fixture.root()).unwrap();
+            project.keep_symlink_paths = true;
+            let (direct, linked) = resolve_both(&mut project);
+            assert!(!Rc::ptr_eq(&direct, &linked));
+            assert!((*linked).borrow().abs_path.contains("Linked"));
+        }
+
+        #[test]
+        fn unreadable_cmake_line_fails_the_project() {
+            let fixture = Fixture::empty();
+            fixture.write_bytes("CMakeLists.txt", b"project(Foo)\n\xff\xfe\n");
+
+            let error = Project::create(fixture.root()).unwrap_err();
+
+            assert!(format!("{:#}", error).contains("Couldn't read line 2"));
+        }
+
+        #[test]
+        fn unreadable_nested_cmake_line_fails_the_project() {
+            let fixture = Fixture::empty();
+            let cmake_includes =
+                fixture.write_bytes("cmake-includes.cmake", b"set(X\n\xff\xfe\n)\n");
+            fixture.write(
+                "CMakeLists.txt",
+                &format!("include(\"{}\")\n", cmake_includes),
+            );
+
+            let error = Project::create(fixture.root()).unwrap_err();
+
+            assert!(format!("{:#}", error).contains("Couldn't read line 2"));
+        }
+
+        #[test]
+        fn cmake_include_through_a_set_variable_is_followed() {
+            let fixture = Fixture::empty();
+            let public_folder = fixture.path("Engine/Source/Runtime/Core/Public");
+            fs::create_dir_all(&public_folder).unwrap();
+            fixture.write(
+                "CMakeLists.txt",
+                &format!(
+                    "set(INC_FILE \"{}\")\ninclude(${{INC_FILE}})\n",
+                    fixture.path("cmake/x-includes.cmake")
+                ),
+            );
+            fixture.write(
+                "cmake/x-includes.cmake",
+                &format!("set(INCLUDE_DIRECTORIES\n\t\"{}\"\n)\n", public_folder),
+            );
+
+            let project = Project::create(fixture.root()).unwrap();
+
+            assert_eq!(
+                project.modules,
+                vec![(
+                    "Engine/Source/Runtime/Core".to_string(),
+                    vec![public_folder]
+                )]
+            );
+        }
+
+        #[test]
+        fn module_priority_resolves_an_ambiguous_include() {
+            let fixture = Fixture::new(&["Engine/Source/Runtime/Aaa", "Engine/Source/Runtime/Bbb"]);
+            let from_aaa = fixture.write("Engine/Source/Runtime/Aaa/Public/Shared.h", "");
+            let from_bbb = fixture.write("Engine/Source/Runtime/Bbb/Public/Shared.h", "");
+            let resolved_path = |project: &mut Project| {
+                let file_info = project
+                    .get_file("Shared.h", "Engine/Source/Runtime/Other")
+                    .unwrap();
+                let abs_path = (*file_info).borrow().abs_path.to_string();
+
+                abs_path
+            };
+
+            let mut project = Project::create(fixture.root()).unwrap();
+            assert_eq!(resolved_path(&mut project), from_aaa);
+
+            let mut project = Project::create(fixture.root()).unwrap();
+            project.module_priority = vec!["Engine/Source/Runtime/Bbb".to_string()];
+            assert_eq!(resolved_path(&mut project), from_bbb);
+        }
+
+        #[test]
+        fn cmake_lists_without_modules_fails_the_project() {
+            let fixture = Fixture::empty();
+            fixture.write(DEFAULT_CMAKE_LISTS, "project(Foo)\n");
+
+            let error = Project::create(fixture.root()).unwrap_err();
+
+            assert!(format!("{:#}", error).contains("No modules were found"));
+        }
+
+        // Only `Public` is registered by the fixture
+        #[test]
+        fn ue_subfolders_resolve_includes_next_to_the_include_path() {
+            let fixture = Fixture::new(&["Engine/Source/Runtime/Core"]);
+
+            for subfolder in ["Private", "Classes", "Internal"] {
+                let header = fixture.write(
+                    &format!("Engine/Source/Runtime/Core/{}/In{}.h", subfolder, subfolder),
+                    "",
+                );
+                let include = format!("In{}.h", subfolder);
+
+                let mut project = Project::create(fixture.root()).unwrap();
+                assert!(project
+                    .get_file(&include, "Engine/Source/Runtime/Core")
+                    .is_err());
+
+                let mut project = Project::create(fixture.root()).unwrap();
+                project.ue_subfolders = true;
+                let file_info = project
+                    .get_file(&include, "Engine/Source/Runtime/Core")
+                    .unwrap();
+
+                assert_eq!(&*(*file_info).borrow().abs_path, header);
+            }
+        }
+
+        // The file is gone by the second lookup, only the cache can still have it
+        #[test]
+        fn repeated_resolution_doesnt_hit_the_filesystem() {
+            let fixture = Fixture::new(&["Engine/Source/Runtime/Core"]);
+            let shared = fixture.write("Engine/Source/Runtime/Core/Public/Shared.h", "");
+            let mut project = Project::create(fixture.root()).unwrap();
+
+            let first = project
+                .get_file("Shared.h", "Engine/Source/Runtime/Core")
+                .unwrap();
+            fs::remove_file(&shared).unwrap();
+            let second = project
+                .get_file("Shared.h", "Engine/Source/Runtime/Core")
+                .unwrap();
+
+            assert!(Rc::ptr_eq(&first, &second));
+        }
+
+        // `Second.cmake` includes `First.cmake` back
+        #[test]
+        fn modules_of_nested_cmake_includes_are_found() {
+            let fixture = Fixture::empty();
+            let public_folder = fixture.path("Engine/Source/Runtime/Core/Public");
+            fs::create_dir_all(&public_folder).unwrap();
+            fixture.write(
+                DEFAULT_CMAKE_LISTS,
+                "include(\"${CMAKE_CURRENT_SOURCE_DIR}/includes/First.cmake\")\n",
+            );
+            fixture.write(
+                "includes/First.cmake",
+                "include(\"${CMAKE_CURRENT_SOURCE_DIR}/includes/Second.cmake\")\n",
+            );
+            fixture.write(
+                "includes/Second.cmake",
+                &format!(
+                    "include(\"${{CMAKE_CURRENT_SOURCE_DIR}}/includes/First.cmake\")\n\
+                     set(INCLUDE_DIRECTORIES\n\t\"{}\"\n)\n",
+                    public_folder
+                ),
+            );
+
+            let project = Project::create(fixture.root()).unwrap();
+
+            assert_eq!(
+                project.modules,
+                vec![(
+                    "Engine/Source/Runtime/Core".to_string(),
+                    vec![public_folder]
+                )]
+            );
+        }
+
+        #[test]
+        fn whole_project_search_registers_the_root_module_once() {
+            let fixture = Fixture::new(&["Engine/Source/Runtime/Core"]);
+            fixture.write("Tools/Scratch/Extra.h", "");
+            fixture.write("Tools/Scratch/Other.h", "");
+
+            let mut project = Project::create(fixture.root()).unwrap();
+            let module_count = project.modules.len();
+
+            project.enable_whole_project_search();
+            project.enable_whole_project_search();
+
+            assert_eq!(project.modules.len(), module_count + 1);
+            assert_eq!(project.modules.last().unwrap().0, PROJECT_ROOT_MODULE);
+
+            assert!(project.get_file("Extra.h", "").is_ok());
+            assert!(project.get_file("Scratch/Other.h", "").is_ok());
+            assert_eq!(project.modules.len(), module_count + 1);
+        }
+
+        #[test]
+        fn cmake_project_keeps_the_scan_options() {
+            let fixture = Fixture::new(&["Engine/Source/Runtime/Core"]);
+            let scan_options = ScanOptions {
+                open_retries: 0,
+                ..ScanOptions::default()
+            };
+
+            let project =
+                Project::create_with_scan_options(fixture.root(), None, &[], &scan_options)
+                    .unwrap();
+
+            assert_eq!(project.scan_options.open_retries, 0);
+        }
+
+        #[test]
+        fn build_cs_project_keeps_the_scan_options() {
+            let fixture = Fixture::empty();
+            fixture.write(
+                "Source/Foo/Foo.Build.cs",
+                "public class Foo : ModuleRules\n{\n}\n",
+            );
+            fs::create_dir_all(fixture.path("Source/Foo/Public")).unwrap();
+
+            let scan_options = ScanOptions {
+                open_retries: 0,
+                ..ScanOptions::default()
+            };
+            let project =
+                Project::create_with_scan_options(fixture.root(), None, &[], &scan_options)
+                    .unwrap();
+
+            assert_eq!(project.modules.len(), 1);
+            assert_eq!(project.scan_options.open_retries, 0);
+        }
+
+        #[test]
+        fn custom_extension_maps_to_its_file_type() {
+            let fixture = Fixture::new(&["Engine/Source/Runtime/Core"]);
+            let header = fixture.write("Engine/Source/Runtime/Core/Public/Foo.hin", "");
+
+            let mut project = Project::create(fixture.root()).unwrap();
+            assert!(project.entry_file_info(&header).is_err());
+
+            let mut project = Project::create(fixture.root()).unwrap();
+            project.scan_options.extensions =
+                HashMap::from([("hin".to_string(), FileType::Header)]);
+            let file_info = project.entry_file_info(&header).unwrap();
+
+            assert_eq!((*file_info).borrow().file_type, FileType::Header);
+        }
+
+        #[test]
+        fn failed_lookups_are_retried_after_a_module_is_added() {
+            let fixture = Fixture::new(&["Engine/Source/Runtime/Core"]);
+            let scratch = fixture.write("Tools/Scratch/Scratch.h", "");
+            fixture.write("ThirdParty/Inc/Lib.h", "");
+            fixture.write("project.rsp", "-IThirdParty/Inc\n");
+
+            let mut project = Project::create(fixture.root()).unwrap();
+
+            assert!(project.get_file("Scratch.h", "").is_err());
+            project.add_ad_hoc_module(&scratch).unwrap();
+            assert!(project.get_file("Scratch.h", "").is_ok());
+
+            assert!(project.get_file("Lib.h", "").is_err());
+            project
+                .add_response_file(&fixture.path("project.rsp"))
+                .unwrap();
+            assert!(project.get_file("Lib.h", "").is_ok());
+        }
+
+        #[test]
+        fn crlf_cmake_files_with_tabs_give_clean_include_paths() {
+            let fixture = Fixture::empty();
+            let public_folder = fixture.path("Engine/Source/Runtime/Core/Public");
+            fs::create_dir_all(&public_folder).unwrap();
+            fixture.write(
+                DEFAULT_CMAKE_LISTS,
+                "project(Foo)\r\n\tinclude(\"${CMAKE_CURRENT_SOURCE_DIR}/cmake-includes.cmake\")\r\n",
+            );
+            fixture.write(
+                "cmake-includes.cmake",
+                &format!(
+                    "set(INCLUDE_DIRECTORIES\r\n\t\"{}\"\r\n)\r\n",
+                    public_folder
+                ),
+            );
+
+            let project = Project::create(fixture.root()).unwrap();
+
+            assert_eq!(
+                project.modules,
+                vec![(
+                    "Engine/Source/Runtime/Core".to_string(),
+                    vec![public_folder]
+                )]
+            );
+        }
+
+        #[test]
+        fn backslash_include_path_gives_the_module() {
+            let fixture = Fixture::empty();
+            let include_path = "C:\\UnrealEngine\\Engine\\Source\\Runtime\\Core\\Public";
+            fixture.write(
+                DEFAULT_CMAKE_LISTS,
+                "include(\"${CMAKE_CURRENT_SOURCE_DIR}/cmake-includes.cmake\")\n",
+            );
+            fixture.write(
+                "cmake-includes.cmake",
+                &format!("set(INCLUDE_DIRECTORIES\n\t\"{}\"\n)\n", include_path),
+            );
+
+            let project = Project::create(fixture.root()).unwrap();
+
+            assert_eq!(
+                project.modules,
+                vec![(
+                    "Engine/Source/Runtime/Core".to_string(),
+                    vec![include_path.to_string()]
+                )]
+            );
+        }
+
+        #[test]
+        fn build_cs_module_is_named_after_the_file() {
+            let fixture = Fixture::empty();
+            fixture.write(
+                "Source/Foo/Foo.Build.cs",
+                "public class Foo : ModuleRules\n{\n\tPublicIncludePaths.Add(\"ThirdParty/Inc\");\n}\n",
+            );
+            fs::create_dir_all(fixture.path("Source/Foo/Public")).unwrap();
+            fs::create_dir_all(fixture.path("Source/Foo/ThirdParty/Inc")).unwrap();
+
+            assert!(Project::has_module_definitions(fixture.root()));
+
+            let project = Project::create(fixture.root()).unwrap();
+            let canonical = |rel_path: &str| {
+                fs::canonicalize(fixture.path(rel_path))
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string()
+            };
+
+            assert_eq!(
+                project.modules,
+                vec![(
+                    "Foo".to_string(),
+                    vec![
+                        canonical("Source/Foo/Public"),
+                        canonical("Source/Foo/ThirdParty/Inc")
+                    ]
+                )]
+            );
+        }
+
+        #[test]
+        fn project_without_cmake_lists_or_build_cs_has_no_module_definitions() {
+            let fixture = Fixture::empty();
+            fixture.write("Source/Foo/Foo.cs", "");
+
+            assert!(!Project::has_module_definitions(fixture.root()));
+        }
+
+        fn default_source_roots() -> Vec<String> {
+            DEFAULT_SOURCE_ROOTS
+                .iter()
+                .map(|root| root.to_string())
+                .collect()
+        }
+
+        #[test]
+        fn plugin_include_path_gives_the_plugin_module() {
+            assert_eq!(
+                Project::module_of_include_path(
+                    "/UnrealEngine/Engine/Plugins/Foo/Source/Bar/Private",
+                    &default_source_roots()
+                ),
+                "Engine/Plugins/Foo/Source/Bar"
+            );
+        }
+
+        #[test]
+        fn game_include_path_gives_the_game_module() {
+            assert_eq!(
+                Project::module_of_include_path(
+                    "/Projects/MyGame/Source/MyGame/Public",
+                    &default_source_roots()
+                ),
+                "Source/MyGame"
+            );
+        }
+
+        #[test]
+        fn include_path_without_a_source_root_gives_the_folder_above_public() {
+            assert_eq!(
+                Project::module_of_include_path(
+                    "/Somewhere/Else/Foo/Public",
+                    &default_source_roots()
+                ),
+                "Foo"
+            );
+        }
+
+        #[test]
+        fn include_path_with_engine_twice_gives_the_last_source_root() {
+            assert_eq!(
+                Project::module_of_include_path(
+                    "/Work/Engine/Mirror/Engine/Source/Runtime/Core/Public",
+                    &default_source_roots()
+                ),
+                "Engine/Source/Runtime/Core"
+            );
+        }
+    }
+}
+
+pub mod metrics {
+    use std::{
+        fmt::{Display, Formatter},
+        time::Duration,
+    };
+
+    use serde_json::{json, Value};
+
+    // Where the time of a run went, tells I/O bound parsing apart from CPU bound traversal
+    #[derive(Clone, Debug, Default)]
+    pub struct Metrics {
+        // Reading the CMake files for the modules
+        pub project_parsing: Duration,
+        // Files are parsed lazily while traversing, this is taken out of `traversal`
+        pub file_parsing: Duration,
+        pub files_parsed: usize,
+        pub traversal: Duration,
+        pub nodes_created: usize,
+    }
+
+    impl Metrics {
+        pub fn average_file_parsing(&self) -> Duration {
+            match self.files_parsed {
+                0 => Duration::ZERO,
+                files_parsed => self.file_parsing / files_parsed as u32,
+            }
+        }
+
+        // Durations in milliseconds
+        pub fn to_json(&self) -> Value {
+            json!({
+                "project_parsing_ms": self.project_parsing.as_secs_f64() * 1000.0,
+                "file_parsing_ms": self.file_parsing.as_secs_f64() * 1000.0,
+                "average_file_parsing_ms": self.average_file_parsing().as_secs_f64() * 1000.0,
+                "files_parsed": self.files_parsed,
+                "traversal_ms": self.traversal.as_secs_f64() * 1000.0,
+                "nodes_created": self.nodes_created,
+            })
+        }
+    }
+
+    impl Display for Metrics {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            writeln!(f, "Project parsing: {:?}", self.project_parsing)?;
+            writeln!(
+                f,
+                "File parsing: {:?} ({} files, {:?} on average)",
+                self.file_parsing,
+                self.files_parsed,
+                self.average_file_parsing()
+            )?;
+            writeln!(
+                f,
+                "Traversal: {:?} ({} nodes)",
+                self.traversal, self.nodes_created
+            )
+        }
+    }
+}
+
+pub mod report {
+    use std::{
+        cell::RefCell,
+        cmp::Ordering,
+        collections::{BTreeMap, HashMap, HashSet},
+        fmt::{Display, Formatter},
+        fs::{self, File},
+        io::{BufReader, Write},
+        path::{Path, PathBuf},
+        rc::Rc,
+        str::FromStr,
+    };
+
+    use anyhow::*;
+    use itertools::Itertools;
+    use serde::{Deserialize, Serialize};
+    use serde_json::{json, Value};
+
+    use crate::{
+        cmp_paths,
+        file_info::{file_name_of, FileInfo, FileType, Include},
+        metrics::Metrics,
+    };
+
+    pub type RecursivePaths = HashMap<String, HashSet<Vec<String>>>;
+
+    // Cycles found from the entry points, plus what was left out of them
+    #[derive(Clone, Debug, Default)]
+    pub struct CycleReport {
+        // Stopped collecting at `TraverseOptions::max_cycles`
+        pub truncated: bool,
+        pub suppressed_cycles: usize,
+        // Formatted suppressions that didn't match any cycle
+        pub stale_suppressions: Vec<String>,
+        pub template_impl_cycles: usize,
+        recursive_paths: RecursivePaths,
+        // Canonical and sorted with `cmp_paths`
+        cycles: Vec<Vec<String>>,
+        modules_by_file: HashMap<String, String>,
+    }
+
+    impl CycleReport {
+        pub fn new(recursive_paths: RecursivePaths, files: &[Rc<RefCell<FileInfo>>]) -> Self {
+            let cycles = canonical_cycles(&recursive_paths)
+                .into_iter()
+                .sorted_by(|cycle1, cycle2| cmp_paths(cycle1, cycle2))
+                .collect();
+
+            Self {
+                recursive_paths,
+                cycles,
+                modules_by_file: modules_by_file(files),
+                ..Default::default()
+            }
+        }
+
+        // Number of distinct cycles
+        pub fn len(&self) -> usize {
+            self.cycles.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.cycles.is_empty()
+        }
+
+        // Distinct cycles, shortest first
+        pub fn iter(&self) -> impl Iterator<Item = &Vec<String>> {
+            self.cycles.iter()
+        }
+
+        pub fn cross_module(&self) -> impl Iterator<Item = &Vec<String>> {
+            self.cycles
+                .iter()
+                .filter(|cycle| !module_crossings(cycle, &self.modules_by_file).is_empty())
+        }
+
+        pub fn mutual_pairs(&self) -> impl Iterator<Item = &Vec<String>> {
+            self.cycles.iter().filter(|cycle| cycle.len() == 2)
+        }
+
+        pub fn longest(&self) -> Option<&Vec<String>> {
+            self.cycles.last()
+        }
+
+        // By file name or by absolute path, whichever the cycles were written with
+        pub fn contains_file(&self, name: &str) -> bool {
+            self.cycles
+                .iter()
+                .flatten()
+                .any(|file| file == name || file_name_of(file) == name)
+        }
+
+        // Every recursive path by the file closing its loop, as the traversal found them
+        pub fn recursive_paths(&self) -> &RecursivePaths {
+            &self.recursive_paths
+        }
+
+        pub fn into_recursive_paths(self) -> RecursivePaths {
+            self.recursive_paths
+        }
+    }
+
+    // What a run found, depends on the analysis mode
+    #[derive(Clone, Debug)]
+    pub enum Findings {
+        Cycles(CycleReport),
+        // File names of every cycle cluster
+        Clusters(Vec<Vec<String>>),
+    }
+
+    impl Findings {
+        // Number of recursive paths or cycle clusters
+        pub fn len(&self) -> usize {
+            match self {
+                Findings::Cycles(cycle_report) => cycle_report
+                    .recursive_paths()
+                    .values()
+                    .map(|paths| paths.len())
+                    .sum(),
+                Findings::Clusters(clusters) => clusters.len(),
+            }
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.len() == 0
+        }
+
+        // Number of canonical cycles or cycle clusters, the same cycle reached through different
+        // paths counts once
+        pub fn distinct_cycles(&self) -> usize {
+            match self {
+                Findings::Cycles(cycle_report) => cycle_report.len(),
+                Findings::Clusters(clusters) => clusters.len(),
+            }
+        }
+    }
+
+    #[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum ReportFormat {
+        Text,
+        Json,
+        // `graph LR` diagram for Markdown docs
+        Mermaid,
+    }
+
+    impl ReportFormat {
+        pub fn from_path(path: &str) -> Self {
+            match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+                Some(ext) if ext.eq_ignore_ascii_case("json") => ReportFormat::Json,
+                Some(ext)
+                    if ext.eq_ignore_ascii_case("mmd") || ext.eq_ignore_ascii_case("mermaid") =>
+                {
+                    ReportFormat::Mermaid
+                }
+                _ => ReportFormat::Text,
+            }
+        }
+    }
+
+    impl FromStr for ReportFormat {
+        type Err = Error;
+
+        fn from_str(name: &str) -> Result<Self> {
+            match name.to_lowercase().as_str() {
+                "text" => Ok(ReportFormat::Text),
+                "json" => Ok(ReportFormat::Json),
+                "mermaid" => Ok(ReportFormat::Mermaid),
+                _ => bail!(
+                    "Unknown report format '{}', expected text, json or mermaid",
+                    name
+                ),
+            }
+        }
+    }
+
+    // Order of the cycles in text reports
+    #[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum CycleOrder {
+        // Shortest first, see `cmp_paths`
+        #[default]
+        Length,
+        Lexicographic,
+        // Spanning the most modules first, the most cross-cutting ones are usually the ones worth
+        // breaking first
+        Modules,
+    }
+
+    impl FromStr for CycleOrder {
+        type Err = Error;
+
+        fn from_str(name: &str) -> Result<Self> {
+            match name.to_lowercase().as_str() {
+                "length" => Ok(CycleOrder::Length),
+                "lexicographic" => Ok(CycleOrder::Lexicographic),
+                "modules" => Ok(CycleOrder::Modules),
+                _ => bail!(
+                    "Unknown cycle order '{}', expected length, lexicographic or modules",
+                    name
+                ),
+            }
+        }
+    }
+
+    impl CycleOrder {
+        pub fn cmp(
+            &self,
+            cycle1: &[String],
+            cycle2: &[String],
+            modules_by_file: &HashMap<String, String>,
+        ) -> Ordering {
+            match self {
+                CycleOrder::Length => cmp_paths(cycle1, cycle2),
+                CycleOrder::Lexicographic => Ord::cmp(cycle1, cycle2),
+                CycleOrder::Modules => Ord::cmp(
+                    &modules_spanned(cycle2, modules_by_file),
+                    &modules_spanned(cycle1, modules_by_file),
+                )
+                .then_with(|| cmp_paths(cycle1, cycle2)),
+            }
+        }
+    }
+
+    #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum Severity {
+        // Headers only, all of them guarded
+        Info,
+        // Headers only, at least one of them unguarded so it likely doesn't compile
+        Warning,
+        // Through a source file, a layering problem
+        Error,
+    }
+
+    impl FromStr for Severity {
+        type Err = Error;
+
+        fn from_str(name: &str) -> Result<Self> {
+            match name.to_lowercase().as_str() {
+                "info" => Ok(Severity::Info),
+                "warning" => Ok(Severity::Warning),
+                "error" => Ok(Severity::Error),
+                _ => bail!(
+                    "Unknown severity '{}', expected info, warning or error",
+                    name
+                ),
+            }
+        }
+    }
+
+    impl Display for Severity {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Severity::Info => write!(f, "info"),
+                Severity::Warning => write!(f, "warning"),
+                Severity::Error => write!(f, "error"),
+            }
+        }
+    }
+
+    // The numbers at the top of a report, all that's written with `stats_only`
+    #[derive(Clone, Debug, Default)]
+    pub struct Summary {
+        pub distinct_cycles: usize,
+        pub mutual_pairs: usize,
+        pub cross_module_cycles: usize,
+        // Number of files in the longest cycle
+        pub longest_cycle: usize,
+        // Modules with at least one file in a cycle
+        pub modules_involved: usize,
+        pub files_parsed: usize,
+        // Blind spot of the analysis, see `FileInfo::skipped_generated_includes`
+        pub skipped_generated_includes: usize,
+        // Most parsed files including the same file of a cycle, breaking that one helps the most
+        pub hub_fan_in: usize,
+        // 0 to 100, higher is better, see `hygiene_score`
+        pub hygiene_score: f64,
+    }
+
+    impl Summary {
+        pub fn create(recursive_paths: &RecursivePaths, files: &[Rc<RefCell<FileInfo>>]) -> Self {
+            let cycles = canonical_cycles(recursive_paths);
+            let modules_by_file = modules_by_file(files);
+
+            // By file name, that's what include strings end with
+            let mut fan_in: HashMap<&str, usize> = HashMap::new();
+            let file_infos = files
+                .iter()
+                .map(|file_info| (*file_info).borrow())
+                .collect::<Vec<_>>();
+            for file_info in file_infos.iter() {
+                for included_name in file_info
+                    .includes
+                    .iter()
+                    .filter_map(|include| include.path.rsplit(['/', '\\']).next())
+                    .unique()
+                {
+                    *fan_in.entry(included_name).or_default() += 1;
+                }
+            }
+
+            let mut summary = Self {
+                distinct_cycles: cycles.len(),
+                mutual_pairs: cycles.iter().filter(|cycle| cycle.len() == 2).count(),
+                cross_module_cycles: cycles
+                    .iter()
+                    .filter(|cycle| !module_crossings(cycle, &modules_by_file).is_empty())
+                    .count(),
+                longest_cycle: cycles
+                    .iter()
+                    .map(|cycle| cycle.len())
+                    .max()
+                    .unwrap_or_default(),
+                modules_involved: cycles
+                    .iter()
+                    .flatten()
+                    .filter_map(|file_name| modules_by_file.get(file_name))
+                    .unique()
+                    .count(),
+                files_parsed: files.len(),
+                skipped_generated_includes: files
+                    .iter()
+                    .map(|file_info| (*file_info).borrow().skipped_generated_includes)
+                    .sum(),
+                hub_fan_in: cycles
+                    .iter()
+                    .flatten()
+                    .filter_map(|cycle_file| fan_in.get(file_name_of(cycle_file)))
+                    .max()
+                    .copied()
+                    .unwrap_or_default(),
+                hygiene_score: 0.0,
+            };
+            summary.hygiene_score = summary.hygiene_score();
+
+            summary
+        }
+
+        // Everything relative to the number of parsed files, so projects of any size compare:
+        //   density = (distinct cycles + cross-module cycles) / files (cross-module count twice)
+        //   length  = longest cycle / files
+        //   hub     = hub fan-in / files
+        //   score   = 100 * (1 - min(1, 0.5 * density + 0.3 * length + 0.2 * hub))
+        // 100 means no cycles at all, 0 a graph tangled beyond repair
+        pub fn hygiene_score(&self) -> f64 {
+            let files = self.files_parsed.max(1) as f64;
+
+            let density = (self.distinct_cycles + self.cross_module_cycles) as f64 / files;
+            let length = self.longest_cycle as f64 / files;
+            let hub = self.hub_fan_in as f64 / files;
+
+            100.0 * (1.0 - (0.5 * density + 0.3 * length + 0.2 * hub).min(1.0))
+        }
+
+        pub fn to_json(&self) -> Value {
+            json!({
+                "distinct_cycles": self.distinct_cycles,
+                "mutual_pairs": self.mutual_pairs,
+                "cross_module_cycles": self.cross_module_cycles,
+                "longest_cycle": self.longest_cycle,
+                "modules_involved": self.modules_involved,
+                "files_parsed": self.files_parsed,
+                "skipped_generated_includes": self.skipped_generated_includes,
+                "hub_fan_in": self.hub_fan_in,
+                "hygiene_score": self.hygiene_score,
+            })
+        }
+    }
+
+    impl Display for Summary {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            writeln!(f, "Distinct cycles: {}", self.distinct_cycles)?;
+            writeln!(f, "Mutual pairs: {}", self.mutual_pairs)?;
+            writeln!(f, "Cross-module cycles: {}", self.cross_module_cycles)?;
+            writeln!(f, "Longest cycle: {} files", self.longest_cycle)?;
+            writeln!(f, "Modules involved: {}", self.modules_involved)?;
+            writeln!(f, "Files parsed: {}", self.files_parsed)?;
+            writeln!(
+                f,
+                "Skipped generated includes: {}",
+                self.skipped_generated_includes
+            )?;
+            writeln!(f, "Hub fan-in: {}", self.hub_fan_in)?;
+            writeln!(f, "Include hygiene score: {:.1}/100", self.hygiene_score)
+        }
+    }
+
+    pub fn write_summary_text(
+        file: &mut impl Write,
+        recursive_paths: &RecursivePaths,
+        files: &[Rc<RefCell<FileInfo>>],
+        metrics: &Metrics,
+    ) -> Result<()> {
+        file.write_all(b"================================================\n")?;
+        file.write_all(
+            Summary::create(recursive_paths, files)
+                .to_string()
+                .as_bytes(),
+        )?;
+        file.write_all(b"================================================\n")?;
+        file.write_all(metrics.to_string().as_bytes())?;
+
+        Ok(())
+    }
+
+    pub fn write_summary_json(
+        file: &mut impl Write,
+        recursive_paths: &RecursivePaths,
+        files: &[Rc<RefCell<FileInfo>>],
+        metrics: &Metrics,
+        truncated: bool,
+    ) -> Result<()> {
+        let mut summary = Summary::create(recursive_paths, files).to_json();
+        summary["truncated"] = json!(truncated);
+
+        serde_json::to_writer_pretty(
+            &mut *file,
+            &json!({
+                "summary": summary,
+                "metrics": metrics.to_json(),
+            }),
+        )?;
+        file.write_all(b"\n")?;
+
+        Ok(())
+    }
+
+    pub fn write_text(
+        file: &mut impl Write,
+        recursive_paths: &RecursivePaths,
+        files: &[Rc<RefCell<FileInfo>>],
+        metrics: &Metrics,
+        cycle_order: CycleOrder,
+    ) -> Result<()> {
+        let cycles = canonical_cycles(recursive_paths);
+        let mutual_pairs = mutual_pairs(recursive_paths);
+
+        let modules_by_file = modules_by_file(files);
+        let file_types = file_types_by_file(files);
+        let file_infos = file_infos_by_file(files);
+        let tag = |cycle: &[String]| {
+            let severity = cycle_severity(cycle, &file_infos);
+
+            if is_template_impl_cycle(cycle, &file_types) {
+                format!(" [{}, template impl]", severity)
+            } else {
+                format!(" [{}]", severity)
+            }
+        };
+        // Mutual pairs get a section of their own
+        let (cross_module_cycles, intra_module_cycles): (Vec<_>, Vec<_>) = cycles
+            .iter()
+            .filter(|cycle| cycle.len() != 2)
+            .sorted_by(|cycle1, cycle2| cycle_order.cmp(cycle1, cycle2, &modules_by_file))
+            .map(|cycle| (cycle, module_crossings(cycle, &modules_by_file)))
+            .partition(|(_cycle, crossings)| !crossings.is_empty());
+
+        file.write_all(b"================================================\n")?;
+        file.write_all(
+            Summary::create(recursive_paths, files)
+                .to_string()
+                .as_bytes(),
+        )?;
+        file.write_all(b"================================================\n")?;
+
+        // Worse than the ones inside a single module, so they come first
+        if !cross_module_cycles.is_empty() {
+            file.write_all(b"Cross-module cycles:\n")?;
+
+            for (cycle, crossings) in cross_module_cycles.iter() {
+                file.write_all(format!("\t{}{}\n", format_cycle(cycle), tag(cycle)).as_bytes())?;
+
+                for (from, to) in crossings.iter() {
+                    file.write_all(format!("\t\t{} -> {}\n", from, to).as_bytes())?;
+                }
+            }
+
+            file.write_all(b"================================================\n")?;
+        }
+
+        if !intra_module_cycles.is_empty() {
+            file.write_all(b"Intra-module cycles:\n")?;
+
+            for (cycle, _crossings) in intra_module_cycles.iter() {
+                let module = cycle
+                    .first()
+                    .and_then(|file_name| modules_by_file.get(file_name))
+                    .map_or("unknown module", |module| module.as_str());
+
+                file.write_all(
+                    format!("\t{}: {}{}\n", module, format_cycle(cycle), tag(cycle)).as_bytes(),
+                )?;
+            }
+
+            file.write_all(b"================================================\n")?;
+        }
+
+        if !mutual_pairs.is_empty() {
+            file.write_all(b"Mutual pairs (usually fixed with a forward declaration):\n")?;
+
+            for pair in mutual_pairs.iter() {
+                file.write_all(format!("\t{}<->{}{}\n", pair[0], pair[1], tag(pair)).as_bytes())?;
+            }
+
+            file.write_all(b"================================================\n")?;
+        }
+
+        for (file_name, paths) in recursive_paths
+            .iter()
+            .sorted_by_key(|(file_name, _)| *file_name)
+        {
+            write_endpoint_text(file, file_name, paths)?;
+        }
+
+        let dynamic_include_files = files
+            .iter()
+            .filter(|file_info| !(*file_info).borrow().dynamic_includes.is_empty())
+            .collect::<Vec<_>>();
+
+        if !dynamic_include_files.is_empty() {
+            file.write_all(b"================================================\n")?;
+            file.write_all(
+                b"Dynamic includes (not resolved, cycles through them can't be found):\n",
+            )?;
+
+            for file_info in dynamic_include_files {
+                let file_info = (*file_info).borrow();
+
+                for dynamic_include in file_info.dynamic_includes.iter() {
+                    file.write_all(
+                        format!("\t{}: {}\n", file_info.file_name, dynamic_include).as_bytes(),
+                    )?;
+                }
+            }
+        }
+
+        // The generated header usually includes little, but when it does, the cycle could be
+        // hiding behind it
+        let generated_cycle_files = cycles
+            .iter()
+            .flatten()
+            .unique()
+            .filter_map(|cycle_file| {
+                files.iter().find(|file_info| {
+                    let file_info = (*file_info).borrow();
+
+                    file_info.skipped_generated_includes > 0
+                        && (*file_info.file_name == **cycle_file
+                            || *file_info.abs_path == **cycle_file)
+                })
+            })
+            .sorted_by_key(|file_info| (*file_info).borrow().file_name.clone())
+            .collect::<Vec<_>>();
+
+        if !generated_cycle_files.is_empty() {
+            file.write_all(b"================================================\n")?;
+            file.write_all(
+                b"Files in cycles that include a (skipped) generated header, more cycles could be \
+                  hiding behind it:\n",
+            )?;
+
+            for file_info in generated_cycle_files {
+                file.write_all(format!("\t{}\n", (*file_info).borrow().file_name).as_bytes())?;
+            }
+        }
+
+        let external_files = files
+            .iter()
+            .filter(|file_info| (*file_info).borrow().external)
+            .collect::<Vec<_>>();
+
+        if !external_files.is_empty() {
+            file.write_all(b"================================================\n")?;
+            file.write_all(b"External includes (outside of the project root):\n")?;
+
+            for file_info in external_files {
+                file.write_all(format!("\t{}\n", (*file_info).borrow().abs_path).as_bytes())?;
+            }
+        }
+
+        let leaf_files = files
+            .iter()
+            .filter(|file_info| (*file_info).borrow().leaf_reason.is_some())
+            .collect::<Vec<_>>();
+
+        if !leaf_files.is_empty() {
+            file.write_all(b"================================================\n")?;
+            file.write_all(
+                b"Unparsed includes (kept as leaves, nothing below them was searched):\n",
+            )?;
+
+            for file_info in leaf_files {
+                let file_info = (*file_info).borrow();
+
+                file.write_all(
+                    format!(
+                        "\t{}: {}\n",
+                        file_info.abs_path,
+                        file_info.leaf_reason.as_deref().unwrap_or_default()
+                    )
+                    .as_bytes(),
+                )?;
+            }
+        }
+
+        // Most unresolved first, those modules' include paths are the likeliest to be wrong
+        let unresolved_include_files = files
+            .iter()
+            .filter(|file_info| !(*file_info).borrow().unresolved_includes.is_empty())
+            .sorted_by_key(|file_info| {
+                let file_info = (*file_info).borrow();
+                (
+                    std::cmp::Reverse(file_info.unresolved_includes.len()),
+                    file_info.file_name.clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        if !unresolved_include_files.is_empty() {
+            file.write_all(b"================================================\n")?;
+            file.write_all(
+                b"Unresolved includes (not found under any include path of the project):\n",
+            )?;
+
+            for file_info in unresolved_include_files {
+                let file_info = (*file_info).borrow();
+
+                file.write_all(
+                    format!(
+                        "\t{}: {} resolved, {} unresolved ({})\n",
+                        file_info.file_name,
+                        file_info.resolved_includes.len(),
+                        file_info.unresolved_includes.len(),
+                        file_info.unresolved_includes.iter().join(", ")
+                    )
+                    .as_bytes(),
+                )?;
+            }
+        }
+
+        file.write_all(b"================================================\n")?;
+        file.write_all(metrics.to_string().as_bytes())?;
+
+        Ok(())
+    }
+
+    // The recursive paths ending at a single file
+    pub fn write_endpoint_text(
+        file: &mut impl Write,
+        file_name: &str,
+        paths: &HashSet<Vec<String>>,
+    ) -> Result<()> {
+        file.write_all(b"------------------------------------------------\n")?;
+
+        file.write_all((format!("{}:\n", file_name)).as_bytes())?;
+
+        let output_paths: Vec<&Vec<String>> = paths
+            .iter()
+            .sorted_by(|path1, path2| cmp_paths(path1, path2))
+            .collect();
+
+        for path in output_paths {
+            file.write_all(format!("\t{}\n", format_path(path)).as_bytes())?;
+        }
+
+        file.write_all("------------------------------------------------\n".as_bytes())?;
+
+        Ok(())
+    }
+
+    // Files reachable from the entry points that aren't part of any cycle, matched by file name
+    // or absolute path since either can be in the cycles
+    pub fn clean_files(
+        recursive_paths: &RecursivePaths,
+        files: &[Rc<RefCell<FileInfo>>],
+    ) -> Vec<Rc<RefCell<FileInfo>>> {
+        let cycle_files: HashSet<String> = canonical_cycles(recursive_paths)
+            .into_iter()
+            .flatten()
+            .collect();
+
+        files
+            .iter()
+            .filter(|file_info| {
+                let file_info = (*file_info).borrow();
+
+                file_info.reached
+                    && !cycle_files.contains(&*file_info.file_name)
+                    && !cycle_files.contains(&*file_info.abs_path)
+            })
+            .sorted_by_key(|file_info| (*file_info).borrow().abs_path.clone())
+            .cloned()
+            .collect()
+    }
+
+    pub fn write_clean_files_text(
+        file: &mut impl Write,
+        recursive_paths: &RecursivePaths,
+        files: &[Rc<RefCell<FileInfo>>],
+    ) -> Result<()> {
+        let clean_files = clean_files(recursive_paths, files);
+
+        file.write_all(b"================================================\n")?;
+        file.write_all(format!("Reachable files in no cycle: {}\n", clean_files.len()).as_bytes())?;
+
+        for file_info in clean_files {
+            file.write_all(format!("\t{}\n", (*file_info).borrow().abs_path).as_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    // The include behind every edge, by (including file, included file). Same keys as
+    // `modules_by_file`, so it works for reports written with either
+    pub fn include_edges(files: &[Rc<RefCell<FileInfo>>]) -> HashMap<(String, String), Include> {
+        let file_names: HashMap<String, String> = files
+            .iter()
+            .map(|file_info| {
+                let file_info = (*file_info).borrow();
+                (
+                    file_info.abs_path.to_string(),
+                    file_info.file_name.to_string(),
+                )
+            })
+            .collect();
+
+        files
+            .iter()
+            .flat_map(|file_info| {
+                let file_info = (*file_info).borrow();
+
+                file_info
+                    .include_edges
+                    .iter()
+                    .flat_map(|(abs_path, include)| {
+                        let file_name = file_names
+                            .get(&**abs_path)
+                            .cloned()
+                            .unwrap_or_else(|| abs_path.to_string());
+
+                        [
+                            (
+                                (file_info.file_name.to_string(), file_name),
+                                include.clone(),
+                            ),
+                            (
+                                (file_info.abs_path.to_string(), abs_path.to_string()),
+                                include.clone(),
+                            ),
+                        ]
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    // Every distinct cycle with the include lines making it up, the lines to edit to break it
+    pub fn write_include_edges_text(
+        file: &mut impl Write,
+        recursive_paths: &RecursivePaths,
+        files: &[Rc<RefCell<FileInfo>>],
+    ) -> Result<()> {
+        let include_edges = include_edges(files);
+
+        file.write_all(b"================================================\n")?;
+        file.write_all(b"Includes making up the cycles:\n")?;
+
+        for cycle in canonical_cycles(recursive_paths)
+            .iter()
+            .sorted_by(|cycle1, cycle2| cmp_paths(cycle1, cycle2))
+        {
+            file.write_all(format!("\t{}\n", format_cycle(cycle)).as_bytes())?;
+
+            for (from, to) in cycle.iter().zip(cycle.iter().cycle().skip(1)) {
+                match include_edges.get(&(from.clone(), to.clone())) {
+                    Some(include) => file.write_all(
+                        format!(
+                            "\t\t{}:{}: {}\n",
+                            from,
+                            include.line_number,
+                            include.line.trim()
+                        )
+                        .as_bytes(),
+                    )?,
+                    None => {
+                        file.write_all(format!("\t\t{} -> {}: unknown\n", from, to).as_bytes())?
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn write_ambiguous_includes_text(
+        file: &mut impl Write,
+        ambiguous_includes: &BTreeMap<String, Vec<(String, PathBuf)>>,
+    ) -> Result<()> {
+        file.write_all(b"================================================\n")?;
+        file.write_all(
+            format!(
+                "Ambiguous includes (more than one module has the file, the first match wins): {}\n",
+                ambiguous_includes.len()
+            )
+            .as_bytes(),
+        )?;
+
+        for (include, candidates) in ambiguous_includes.iter() {
+            file.write_all(format!("\t{}:\n", include).as_bytes())?;
+
+            for (modl, path) in candidates.iter() {
+                file.write_all(format!("\t\t{} ({})\n", path.display(), modl).as_bytes())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // A `<file name>.txt` per file closing a loop in the `dir` directory, for reports too big to
+    // navigate as a single file. Path separators and the like are replaced in the names
+    pub fn write_split_text(dir: &str, recursive_paths: &RecursivePaths) -> Result<()> {
+        fs::create_dir_all(Path::new(dir))
+            .with_context(|| format!("Couldn't create the output directory: {}", dir))?;
+
+        for (file_name, paths) in recursive_paths.iter() {
+            let sanitized_name: String = file_name
+                .chars()
+                .map(|c| match c {
+                    '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+                    c => c,
+                })
+                .collect();
+
+            let endpoint_path = Path::new(dir).join(format!("{}.txt", sanitized_name));
+            let mut file = File::create(&endpoint_path)
+                .with_context(|| format!("Couldn't create {}", endpoint_path.display()))?;
+
+            write_endpoint_text(&mut file, file_name, paths)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn write_clusters_text(
+        file: &mut impl Write,
+        clusters: &[Vec<Rc<RefCell<FileInfo>>>],
+        metrics: &Metrics,
+    ) -> Result<()> {
+        file.write_all(b"================================================\n")?;
+        file.write_all(format!("Cycle clusters: {}\n", clusters.len()).as_bytes())?;
+        file.write_all(b"================================================\n")?;
+
+        for (cluster_ind, cluster) in clusters.iter().enumerate() {
+            file.write_all(b"------------------------------------------------\n")?;
+            file.write_all(
+                format!("Cluster {} ({} files):\n", cluster_ind + 1, cluster.len()).as_bytes(),
+            )?;
+
+            for file_info in cluster.iter() {
+                file.write_all(format!("\t{}\n", (*file_info).borrow().file_name).as_bytes())?;
+            }
+
+            file.write_all(b"------------------------------------------------\n")?;
+        }
+
+        file.write_all(b"================================================\n")?;
+        file.write_all(metrics.to_string().as_bytes())?;
+
+        Ok(())
+    }
+
+    pub fn write_clusters_json(
+        file: &mut impl Write,
+        clusters: &[Vec<Rc<RefCell<FileInfo>>>],
+        metrics: &Metrics,
+    ) -> Result<()> {
+        let cluster_names: Vec<Vec<String>> = clusters
+            .iter()
+            .map(|cluster| {
+                cluster
+                    .iter()
+                    .map(|file_info| (*file_info).borrow().file_name.to_string())
+                    .collect()
+            })
+            .collect();
+
+        serde_json::to_writer_pretty(
+            &mut *file,
+            &json!({ "clusters": cluster_names, "metrics": metrics.to_json() }),
+        )?;
+        file.write_all(b"\n")?;
+
+        Ok(())
+    }
+
+    // Keys and paths are sorted so that the same tree always produces the same JSON, `truncated`
+    // tells the readers that `max_cycles` cut the paths short
+    pub fn write_json(
+        file: &mut impl Write,
+        recursive_paths: &RecursivePaths,
+        files: &[Rc<RefCell<FileInfo>>],
+        metrics: &Metrics,
+        truncated: bool,
+    ) -> Result<()> {
+        let sorted_paths: BTreeMap<&String, Vec<&Vec<String>>> = recursive_paths
+            .iter()
+            .map(|(file_name, paths)| {
+                (
+                    file_name,
+                    paths
+                        .iter()
+                        .sorted_by(|path1, path2| cmp_paths(path1, path2))
+                        .collect(),
+                )
+            })
+            .collect();
+
+        let mut summary = Summary::create(recursive_paths, files).to_json();
+        summary["truncated"] = json!(truncated);
+
+        let file_infos = file_infos_by_file(files);
+        let cycles: Vec<Value> = canonical_cycles(recursive_paths)
+            .iter()
+            .sorted_by(|cycle1, cycle2| cmp_paths(cycle1, cycle2))
+            .map(|cycle| {
+                json!({
+                    "cycle": cycle,
+                    "severity": cycle_severity(cycle, &file_infos).to_string(),
+                })
+            })
+            .collect();
+
+        serde_json::to_writer_pretty(
+            &mut *file,
+            &json!({
+                "summary": summary,
+                "recursive_paths": sorted_paths,
+                "cycles": cycles,
+                "metrics": metrics.to_json(),
+            }),
+        )?;
+        file.write_all(b"\n")?;
+
+        Ok(())
+    }
+
+    // Every edge of every path, the ones inside a cycle drawn thick (`==>`) and the ones leading
+    // to it from the entry point as normal arrows
+    pub fn write_mermaid(file: &mut impl Write, recursive_paths: &RecursivePaths) -> Result<()> {
+        let mut node_ids: BTreeMap<&String, String> = BTreeMap::new();
+        let mut edges: BTreeMap<(&String, &String), bool> = BTreeMap::new();
+
+        for path in recursive_paths.values().flatten() {
+            let cycle_start = path
+                .iter()
+                .position(|file_name| Some(file_name) == path.last())
+                .unwrap_or_default();
+
+            for (edge_ind, (from, to)) in path.iter().tuple_windows().enumerate() {
+                *edges.entry((from, to)).or_default() |= edge_ind >= cycle_start;
+            }
+        }
+
+        for &(from, to) in edges.keys() {
+            for file_name in [from, to] {
+                if !node_ids.contains_key(file_name) {
+                    let node_id = mermaid_id(file_name, node_ids.values());
+                    node_ids.insert(file_name, node_id);
+                }
+            }
+        }
+
+        file.write_all(b"graph LR\n")?;
+
+        for (file_name, node_id) in node_ids.iter() {
+            file.write_all(
+                format!(
+                    "    {}[\"{}\"]\n",
+                    node_id,
+                    file_name.replace('"', "#quot;")
+                )
+                .as_bytes(),
+            )?;
+        }
+
+        for ((from, to), in_cycle) in edges.iter() {
+            let arrow = if *in_cycle { "==>" } else { "-->" };
+
+            file.write_all(
+                format!("    {} {} {}\n", node_ids[from], arrow, node_ids[to]).as_bytes(),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    // Only letters, digits and underscores, with a number added when two file names end up the
+    // same (`A.h` and `A_h`)
+    fn mermaid_id<'a>(file_name: &str, taken: impl Iterator<Item = &'a String> + Clone) -> String {
+        let sanitized: String = file_name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+
+        let mut node_id = sanitized.clone();
+        let mut suffix = 1;
+
+        while taken.clone().any(|taken_id| *taken_id == node_id) {
+            suffix += 1;
+            node_id = format!("{}_{}", sanitized, suffix);
+        }
+
+        node_id
+    }
+
+    pub fn read_json(path: &str) -> Result<RecursivePaths> {
+        let file = File::open(Path::new(path))
+            .with_context(|| format!("Couldn't open the report: {}", path))?;
+
+        let report: JsonReport = serde_json::from_reader(BufReader::new(file))
+            .with_context(|| format!("Couldn't parse the report: {}", path))?;
+
+        Ok(report.recursive_paths)
+    }
+
+    // Known and accepted cycles, one per line as `A.h->B.h->C.h` (the closing `->A.h` is optional).
+    // Blank lines and `#` comments are skipped. The cycles come back canonical
+    pub fn read_suppressions(path: &str) -> Result<HashSet<Vec<String>>> {
+        let contents = fs::read_to_string(Path::new(path))
+            .with_context(|| format!("Couldn't read the suppressions file: {}", path))?;
+
+        Ok(contents
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let mut cycle: Vec<String> = line
+                    .split("->")
+                    .map(|file_name| file_name.trim().to_string())
+                    .collect();
+
+                if cycle.len() > 1 && cycle.first() == cycle.last() {
+                    cycle.pop();
+                }
+                cycle.push(cycle[0].clone());
+
+                canonical_cycle(&cycle)
+            })
+            .collect())
+    }
+
+    // Only what's needed back from a JSON report, the metrics are left out
+    #[derive(Deserialize)]
+    struct JsonReport {
+        recursive_paths: RecursivePaths,
+    }
+
+    // Reported paths go from the entry point to the file that closed the loop, so only the part
+    // starting at the first occurrence of that file is the actual cycle. It's rotated to start at
+    // the smallest file name so that the same cycle reached from different places compares equal
+    pub fn canonical_cycle(path: &[String]) -> Vec<String> {
+        let last = match path.last() {
+            Some(last) => last,
+            None => return vec![],
+        };
+
+        let start = path.iter().position(|file_name| file_name == last).unwrap();
+        let mut cycle = path[start..path.len() - 1].to_vec();
+
+        if cycle.is_empty() {
+            cycle.push(last.clone());
+        }
+
+        if let Some((min_ind, _)) = cycle.iter().enumerate().min_by_key(|(_, name)| *name) {
+            cycle.rotate_left(min_ind);
+        }
+
+        cycle
+    }
+
+    pub fn canonical_cycles(recursive_paths: &RecursivePaths) -> HashSet<Vec<String>> {
+        recursive_paths
+            .values()
+            .flatten()
+            .map(|path| canonical_cycle(path))
+            .collect()
+    }
+
+    // Two files including each other, the most common and easiest to fix kind of cycle
+    pub fn mutual_pairs(recursive_paths: &RecursivePaths) -> Vec<Vec<String>> {
+        canonical_cycles(recursive_paths)
+            .into_iter()
+            .filter(|cycle| cycle.len() == 2)
+            .sorted()
+            .collect()
+    }
+
+    // UE relies on header names being unique, so the file name is enough to find the module
+    // Keyed by the absolute path as well, for reports written with absolute paths
+    pub fn modules_by_file(files: &[Rc<RefCell<FileInfo>>]) -> HashMap<String, String> {
+        files
+            .iter()
+            .flat_map(|file_info| {
+                let file_info = (*file_info).borrow();
+                [
+                    (
+                        file_info.file_name.to_string(),
+                        file_info.module.to_string(),
+                    ),
+                    (file_info.abs_path.to_string(), file_info.module.to_string()),
+                ]
+            })
+            .collect()
+    }
+
+    // Same keys as `modules_by_file`
+    pub fn file_infos_by_file(
+        files: &[Rc<RefCell<FileInfo>>],
+    ) -> HashMap<String, Rc<RefCell<FileInfo>>> {
+        files
+            .iter()
+            .flat_map(|file_info| {
+                let (file_name, abs_path) = {
+                    let file_info = (*file_info).borrow();
+                    (
+                        file_info.file_name.to_string(),
+                        file_info.abs_path.to_string(),
+                    )
+                };
+
+                [
+                    (file_name, file_info.clone()),
+                    (abs_path, file_info.clone()),
+                ]
+            })
+            .collect()
+    }
+
+    // Files of the cycle missing from `file_infos` count as unguarded headers
+    pub fn cycle_severity(
+        cycle: &[String],
+        file_infos: &HashMap<String, Rc<RefCell<FileInfo>>>,
+    ) -> Severity {
+        cycle
+            .iter()
+            .map(|file_name| match file_infos.get(file_name) {
+                Some(file_info) => {
+                    let file_info = (*file_info).borrow();
+
+                    if file_info.file_type == FileType::Source {
+                        Severity::Error
+                    } else if file_info.guarded {
+                        Severity::Info
+                    } else {
+                        Severity::Warning
+                    }
+                }
+                None => Severity::Warning,
+            })
+            .max()
+            .unwrap_or(Severity::Info)
+    }
+
+    // Same keys as `modules_by_file`
+    pub fn file_types_by_file(files: &[Rc<RefCell<FileInfo>>]) -> HashMap<String, FileType> {
+        files
+            .iter()
+            .flat_map(|file_info| {
+                let file_info = (*file_info).borrow();
+                [
+                    (file_info.file_name.to_string(), file_info.file_type),
+                    (file_info.abs_path.to_string(), file_info.file_type),
+                ]
+            })
+            .collect()
+    }
+
+    // A header including its own implementation file at the end (`Foo.hpp` -> `Foo_impl.cpp`,
+    // `Foo.h` -> `Foo.inl`), which includes the header back. Common for templates and harmless,
+    // recognized by the implementation's name starting with the header's
+    pub fn is_template_impl_cycle(
+        cycle: &[String],
+        file_types: &HashMap<String, FileType>,
+    ) -> bool {
+        if cycle.len() != 2 {
+            return false;
+        }
+
+        let file_type = |file_name: &String| file_types.get(file_name).copied();
+        let file_stem = |file_name: &String| {
+            Path::new(file_name)
+                .file_stem()
+                .map(|file_stem| file_stem.to_string_lossy().to_lowercase())
+                .unwrap_or_default()
+        };
+
+        let (header, implementation) = match (file_type(&cycle[0]), file_type(&cycle[1])) {
+            (Some(FileType::Header), Some(FileType::Inline | FileType::Source)) => {
+                (&cycle[0], &cycle[1])
+            }
+            (Some(FileType::Inline | FileType::Source), Some(FileType::Header)) => {
+                (&cycle[1], &cycle[0])
+            }
+            _ => return false,
+        };
+
+        let header_stem = file_stem(header);
+        !header_stem.is_empty() && file_stem(implementation).starts_with(&header_stem)
+    }
+
+    // Distinct modules of the cycle's files, files without a known module aren't counted
+    pub fn modules_spanned(cycle: &[String], modules_by_file: &HashMap<String, String>) -> usize {
+        cycle
+            .iter()
+            .filter_map(|file_name| modules_by_file.get(file_name))
+            .unique()
+            .count()
+    }
+
+    // Every edge of the cycle (including the closing one) going from one module to another, as
+    // `(from module, to module)`. Empty for cycles inside a single module
+    pub fn module_crossings(
+        cycle: &[String],
+        modules_by_file: &HashMap<String, String>,
+    ) -> Vec<(String, String)> {
+        let modules = cycle
+            .iter()
+            .map(|file_name| modules_by_file.get(file_name))
+            .collect::<Vec<_>>();
+
+        modules
+            .iter()
+            .zip(modules.iter().cycle().skip(1))
+            .filter_map(|(from, to)| match (from, to) {
+                (Some(from), Some(to)) if from != to => Some((from.to_string(), to.to_string())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn format_cycle(cycle: &[String]) -> String {
+        match cycle {
+            [file_name] => format!("{}->{} (self)", file_name, file_name),
+            [first, ..] => format!("{}->{}", cycle.join("->"), first),
+            [] => String::new(),
+        }
+    }
+
+    // Paths start at the entry point, so a file's index is its include depth when the cycle was
+    // found. Only the files of the cycle itself are annotated, the repeated last one is left out
+    pub fn format_path(path: &[String]) -> String {
+        let cycle_start = path
+            .last()
+            .and_then(|last| path.iter().position(|file_name| file_name == last))
+            .unwrap_or_default();
+
+        let formatted_path = path
+            .iter()
+            .enumerate()
+            .map(|(depth, file_name)| {
+                if depth >= cycle_start && depth + 1 < path.len() {
+                    format!("{}[depth {}]", file_name, depth)
+                } else {
+                    file_name.clone()
+                }
+            })
+            .join("->");
+
+        match cycle_start + 2 == path.len() {
+            true => format!("{} (self)", formatted_path),
+            false => formatted_path,
+        }
+    }
+
+    pub struct CycleDiff {
+        pub added: Vec<Vec<String>>,
+        pub removed: Vec<Vec<String>>,
+    }
+
+    pub fn diff(old: &RecursivePaths, new: &RecursivePaths) -> CycleDiff {
+        let old_cycles = canonical_cycles(old);
+        let new_cycles = canonical_cycles(new);
+
+        CycleDiff {
+            added: new_cycles
+                .difference(&old_cycles)
+                .cloned()
+                .sorted_by(|cycle1, cycle2| cmp_paths(cycle1, cycle2))
+                .collect(),
+            removed: old_cycles
+                .difference(&new_cycles)
+                .cloned()
+                .sorted_by(|cycle1, cycle2| cmp_paths(cycle1, cycle2))
+                .collect(),
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::test_fixture::Fixture;
+
+        #[test]
+        fn mutual_pairs_are_only_listed_in_their_own_section() {
+            let path = |files: &[&str]| {
+                files
+                    .iter()
+                    .map(|file| file.to_string())
+                    .collect::<Vec<_>>()
+            };
+            let recursive_paths: RecursivePaths = HashMap::from([
+                (
+                    "A.h".to_string(),
+                    HashSet::from([path(&["A.h", "B.h", "A.h"])]),
+                ),
+                (
+                    "C.h".to_string(),
+                    HashSet::from([path(&["C.h", "D.h", "E.h", "C.h"])]),
+                ),
+            ]);
+
+            let mut text = vec![];
+            write_text(
+                &mut text,
+                &recursive_paths,
+                &[],
+                &Metrics::default(),
+                CycleOrder::default(),
+            )
+            .unwrap();
+            let text = String::from_utf8(text).unwrap();
+
+            let section = |title: &str| {
+                let start = text.find(title).unwrap();
+                let end = text[start..].find("=====").unwrap();
+                text[start..start + end].to_string()
+            };
+
+            assert!(section("Intra-module cycles:").contains("C.h"));
+            assert!(!section("Intra-module cycles:").contains("A.h"));
+            assert!(section("Mutual pairs (").contains("A.h<->B.h"));
+        }
+
+        #[test]
+        fn json_report_reads_back_the_same_recursive_paths() {
+            let fixture = Fixture::empty();
+            let report_path = fixture.path("report.json");
+            let recursive_paths: RecursivePaths = HashMap::from([
+                (
+                    "A.h".to_string(),
+                    HashSet::from([
+                        vec!["A.h".to_string(), "B.h".to_string(), "A.h".to_string()],
+                        vec![
+                            "Entry.h".to_string(),
+                            "A.h".to_string(),
+                            "C.h".to_string(),
+                            "A.h".to_string(),
+                        ],
+                    ]),
+                ),
+                (
+                    "D.h".to_string(),
+                    HashSet::from([vec!["D.h".to_string(), "D.h".to_string()]]),
+                ),
+            ]);
+
+            let mut file = File::create(&report_path).unwrap();
+            write_json(&mut file, &recursive_paths, &[], &Metrics::default(), false).unwrap();
+            drop(file);
+
+            assert_eq!(read_json(&report_path).unwrap(), recursive_paths);
+        }
+    }
+}
+
+pub mod options {
+    use std::{
+        collections::{HashMap, HashSet},
+        fs,
+        path::Path,
+    };
+
+    use anyhow::*;
+    use serde::Deserialize;
+
+    use crate::{
+        file_info::{FileType, ScanOptions, DEFAULT_OPEN_RETRIES},
+        node::{TraverseOptions, DEFAULT_MAX_CYCLES},
+        report::{CycleOrder, ReportFormat, Severity},
+    };
+
+    #[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum AnalysisMode {
+        // Depth first traversal from the entry points
+        #[default]
+        EntryPoints,
+        // Strongly connected components of the include graph of the whole project (or the
+        // `scc_module`), finds every cycle group regardless of entry points
+        Scc,
+    }
+
+    // Everything a single analysis run needs, defaults match the behavior of the GUI
+    #[derive(Clone, Debug, Default)]
+    pub struct AnalysisOptions {
+        pub mode: AnalysisMode,
+        pub scc_module: Option<String>,
+        pub project_path: String,
+        // CMake file the modules are read from, `CMakeLists.txt` in the project root if `None`
+        pub cmake_lists: Option<String>,
+        // Folders the cmake module paths start at, `DEFAULT_SOURCE_ROOTS` if empty
+        pub source_roots: Vec<String>,
+        pub entry_points: Vec<String>,
+        // File listing more entry points, one per line. Blank lines and `#` comments are skipped
+        pub entry_points_file: Option<String>,
+        // Abort the run on an entry point that can't be opened, instead of skipping it with a
+        // warning
+        pub strict_entry_points: bool,
+        // Give the listed entry points that aren't under any include path a module of their own,
+        // see `Project::add_ad_hoc_module`. Not for the changed files
+        pub ad_hoc_entry_points: bool,
+        // Git ref to diff the project against, the changed files of supported types become
+        // additional entry points and only cycles going through one of them are reported
+        pub changed_since: Option<String>,
+        pub output_file_path: String,
+        // `None` picks the format from the output file extension
+        pub format: Option<ReportFormat>,
+        // Add to the end of the output file instead of overwriting it
+        pub append: bool,
+        // The output path is a directory getting a text file per file closing a loop, see
+        // `report::write_split_text`
+        pub split_by_endpoint: bool,
+        // Only write the summary and metrics, without the (possibly huge) list of cycles
+        pub stats_only: bool,
+        // Leave out cycles of fewer files, e.g. 3 hides the mutual pairs
+        pub min_cycle_length: Option<usize>,
+        // Leave out cycles less severe than this, see `report::cycle_severity`
+        pub min_severity: Option<Severity>,
+        // Known and accepted cycles left out of the report, see `report::read_suppressions`
+        pub suppressions_file: Option<String>,
+        // Keep the cycles of a header including its own implementation file, see
+        // `report::is_template_impl_cycle`, they're left out otherwise
+        pub keep_template_impl_cycles: bool,
+        // Fail the run if more distinct cycles than this are found, for ratcheting in CI
+        pub max_allowed_cycles: Option<usize>,
+        // Also list the reachable files that aren't in any cycle, text reports only
+        pub list_clean_files: bool,
+        // Also list the include line behind every edge of the cycles, text reports only
+        pub show_include_lines: bool,
+        pub cycle_order: CycleOrder,
+        pub keep_symlink_paths: bool,
+        pub module_priority: Vec<String>,
+        // Alias -> module name, see `Project::module_aliases`
+        pub module_aliases: HashMap<String, String>,
+        pub pair_units: bool,
+        pub ue_subfolders: bool,
+        // Look for includes no include path resolves anywhere under the project root
+        pub search_whole_project: bool,
+        // Report the includes more than one module has a file for, text reports only
+        pub diagnose_ambiguity: bool,
+        // Compiler response file whose `-I`/`-isystem` paths are used as a catch-all module
+        pub response_file: Option<String>,
+        pub scan_options: ScanOptions,
+        pub traverse_options: TraverseOptions,
+    }
+
+    impl AnalysisOptions {
+        pub fn new(project_path: &str, entry_point: &str, output_file_path: &str) -> Self {
+            Self {
+                project_path: project_path.to_string(),
+                entry_points: vec![entry_point.to_string()],
+                output_file_path: output_file_path.to_string(),
+                ..Default::default()
+            }
+        }
+
+        pub fn scc(project_path: &str, module: Option<&str>, output_file_path: &str) -> Self {
+            Self {
+                mode: AnalysisMode::Scc,
+                scc_module: module.map(|module| module.to_string()),
+                project_path: project_path.to_string(),
+                output_file_path: output_file_path.to_string(),
+                ..Default::default()
+            }
+        }
+
+        pub fn cmake_lists(mut self, cmake_lists: &str) -> Self {
+            self.cmake_lists = Some(cmake_lists.to_string());
+            self
+        }
+
+        pub fn source_root(mut self, source_root: &str) -> Self {
+            self.source_roots.push(source_root.to_string());
+            self
+        }
+
+        pub fn entry_point(mut self, entry_point: &str) -> Self {
+            self.entry_points.push(entry_point.to_string());
+            self
+        }
+
+        pub fn entry_points_file(mut self, entry_points_file: &str) -> Self {
+            self.entry_points_file = Some(entry_points_file.to_string());
+            self
+        }
+
+        pub fn strict_entry_points(mut self, strict_entry_points: bool) -> Self {
+            self.strict_entry_points = strict_entry_points;
+            self
+        }
+
+        pub fn ad_hoc_entry_points(mut self, ad_hoc_entry_points: bool) -> Self {
+            self.ad_hoc_entry_points = ad_hoc_entry_points;
+            self
+        }
+
+        pub fn changed_since(mut self, base_ref: &str) -> Self {
+            self.changed_since = Some(base_ref.to_string());
+            self
+        }
+
+        pub fn format(mut self, format: ReportFormat) -> Self {
+            self.format = Some(format);
+            self
+        }
+
+        pub fn append(mut self, append: bool) -> Self {
+            self.append = append;
+            self
+        }
+
+        pub fn split_by_endpoint(mut self, split_by_endpoint: bool) -> Self {
+            self.split_by_endpoint = split_by_endpoint;
+            self
+        }
+
+        pub fn stats_only(mut self, stats_only: bool) -> Self {
+            self.stats_only = stats_only;
+            self
+        }
+
+        pub fn min_cycle_length(mut self, min_cycle_length: usize) -> Self {
+            self.min_cycle_length = Some(min_cycle_length);
+            self
+        }
+
+        pub fn min_severity(mut self, min_severity: Severity) -> Self {
+            self.min_severity = Some(min_severity);
+            self
+        }
+
+        pub fn suppressions_file(mut self, suppressions_file: &str) -> Self {
+            self.suppressions_file = Some(suppressions_file.to_string());
+            self
+        }
+
+        pub fn keep_template_impl_cycles(mut self, keep_template_impl_cycles: bool) -> Self {
+            self.keep_template_impl_cycles = keep_template_impl_cycles;
+            self
+        }
+
+        pub fn max_allowed_cycles(mut self, max_allowed_cycles: usize) -> Self {
+            self.max_allowed_cycles = Some(max_allowed_cycles);
+            self
+        }
+
+        pub fn list_clean_files(mut self, list_clean_files: bool) -> Self {
+            self.list_clean_files = list_clean_files;
+            self
+        }
+
+        pub fn show_include_lines(mut self, show_include_lines: bool) -> Self {
+            self.show_include_lines = show_include_lines;
+            self
+        }
+
+        pub fn cycle_order(mut self, cycle_order: CycleOrder) -> Self {
+            self.cycle_order = cycle_order;
+            self
+        }
+
+        pub fn keep_symlink_paths(mut self, keep_symlink_paths: bool) -> Self {
+            self.keep_symlink_paths = keep_symlink_paths;
+            self
+        }
+
+        pub fn module_priority(mut self, module_priority: Vec<String>) -> Self {
+            self.module_priority = module_priority;
+            self
+        }
+
+        pub fn module_alias(mut self, alias: &str, module: &str) -> Self {
+            self.module_aliases
+                .insert(alias.to_string(), module.to_string());
+            self
+        }
+
+        pub fn pair_units(mut self, pair_units: bool) -> Self {
+            self.pair_units = pair_units;
+            self
+        }
+
+        pub fn ue_subfolders(mut self, ue_subfolders: bool) -> Self {
+            self.ue_subfolders = ue_subfolders;
+            self
+        }
+
+        pub fn search_whole_project(mut self, search_whole_project: bool) -> Self {
+            self.search_whole_project = search_whole_project;
+            self
+        }
+
+        pub fn diagnose_ambiguity(mut self, diagnose_ambiguity: bool) -> Self {
+            self.diagnose_ambiguity = diagnose_ambiguity;
+            self
+        }
+
+        pub fn response_file(mut self, response_file: &str) -> Self {
+            self.response_file = Some(response_file.to_string());
+            self
+        }
+
+        pub fn include_cutoff(mut self, include_cutoff: usize) -> Self {
+            self.scan_options.include_cutoff = Some(include_cutoff);
+            self
+        }
+
+        pub fn open_retries(mut self, open_retries: usize) -> Self {
+            self.scan_options.open_retries = open_retries;
+            self
+        }
+
+        pub fn extension(mut self, extension: &str, file_type: FileType) -> Self {
+            self.scan_options
+                .extensions
+                .insert(extension.to_string(), file_type);
+            self
+        }
+
+        pub fn allowed_file_types(mut self, allowed_file_types: HashSet<FileType>) -> Self {
+            self.traverse_options.allowed_file_types = Some(allowed_file_types);
+            self
+        }
+
+        pub fn root_sources_only(mut self, root_sources_only: bool) -> Self {
+            self.traverse_options.root_sources_only = root_sources_only;
+            self
+        }
+
+        pub fn pch_pattern(mut self, pch_pattern: &str) -> Self {
+            self.traverse_options
+                .pch_patterns
+                .push(pch_pattern.to_string());
+            self
+        }
+
+        pub fn only_module(mut self, only_module: &str) -> Self {
+            self.traverse_options.only_module = Some(only_module.to_string());
+            self
+        }
+
+        pub fn excluded_modules(mut self, excluded_modules: HashSet<String>) -> Self {
+            self.traverse_options.excluded_modules = excluded_modules;
+            self
+        }
+
+        pub fn skip_imports(mut self, skip_imports: bool) -> Self {
+            self.traverse_options.skip_imports = skip_imports;
+            self
+        }
+
+        pub fn abs_paths(mut self, abs_paths: bool) -> Self {
+            self.traverse_options.abs_paths = abs_paths;
+            self
+        }
+
+        pub fn fail_fast(mut self, fail_fast: bool) -> Self {
+            self.traverse_options.fail_fast = fail_fast;
+            self
+        }
+
+        pub fn minimal_cycles(mut self, minimal_cycles: bool) -> Self {
+            self.traverse_options.minimal_cycles = minimal_cycles;
+            self
+        }
+
+        // `None` collects every recursive path
+        pub fn max_cycles(mut self, max_cycles: Option<usize>) -> Self {
+            self.traverse_options.max_cycles = max_cycles;
+            self
+        }
+
+        pub fn max_depth(mut self, max_depth: Option<usize>) -> Self {
+            self.traverse_options.max_depth = max_depth;
+            self
+        }
+
+        // Reads every `[[job]]` of a TOML manifest, so several analyses can run in one go
+        pub fn from_manifest(manifest_path: &str) -> Result<Vec<Self>> {
+            let manifest = fs::read_to_string(Path::new(manifest_path))
+                .with_context(|| format!("Couldn't read the manifest: {}", manifest_path))?;
+
+            let manifest: Manifest = toml::from_str(&manifest)
+                .with_context(|| format!("Couldn't parse the manifest: {}", manifest_path))?;
+
+            Ok(manifest.job.into_iter().map(Self::from).collect())
+        }
+    }
+
+    #[derive(Deserialize)]
+    struct Manifest {
+        #[serde(default)]
+        job: Vec<ManifestJob>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct ManifestJob {
+        #[serde(default)]
+        mode: AnalysisMode,
+        scc_module: Option<String>,
+        project: String,
+        cmake_lists: Option<String>,
+        #[serde(default)]
+        source_roots: Vec<String>,
+        #[serde(default)]
+        entry_points: Vec<String>,
+        entry_points_file: Option<String>,
+        #[serde(default)]
+        strict_entry_points: bool,
+        #[serde(default)]
+        ad_hoc_entry_points: bool,
+        changed_since: Option<String>,
+        output: String,
+        format: Option<ReportFormat>,
+        #[serde(default)]
+        append: bool,
+        #[serde(default)]
+        split_by_endpoint: bool,
+        #[serde(default)]
+        stats_only: bool,
+        min_cycle_length: Option<usize>,
+        min_severity: Option<Severity>,
+        suppressions_file: Option<String>,
+        #[serde(default)]
+        keep_template_impl_cycles: bool,
+        max_allowed_cycles: Option<usize>,
+        #[serde(default)]
+        list_clean_files: bool,
+        #[serde(default)]
+        show_include_lines: bool,
+        #[serde(default)]
+        cycle_order: CycleOrder,
+        #[serde(default)]
+        keep_symlink_paths: bool,
+        #[serde(default)]
+        module_priority: Vec<String>,
+        #[serde(default)]
+        module_aliases: HashMap<String, String>,
+        #[serde(default)]
+        pair_units: bool,
+        #[serde(default)]
+        ue_subfolders: bool,
+        #[serde(default)]
+        search_whole_project: bool,
+        #[serde(default)]
+        diagnose_ambiguity: bool,
+        response_file: Option<String>,
+        include_cutoff: Option<usize>,
+        open_retries: Option<usize>,
+        // Extension (without the dot) -> file type
+        #[serde(default)]
+        extensions: HashMap<String, FileType>,
+        allowed_file_types: Option<HashSet<FileType>>,
+        #[serde(default)]
+        root_sources_only: bool,
+        #[serde(default)]
+        excluded_modules: HashSet<String>,
+        only_module: Option<String>,
+        #[serde(default)]
+        pch_patterns: Vec<String>,
+        #[serde(default)]
+        skip_imports: bool,
+        #[serde(default)]
+        abs_paths: bool,
+        #[serde(default)]
+        fail_fast: bool,
+        #[serde(default)]
+        minimal_cycles: bool,
+        // 0 disables the limit
+        max_cycles: Option<usize>,
+        max_depth: Option<usize>,
+    }
+
+    impl From<ManifestJob> for AnalysisOptions {
+        fn from(job: ManifestJob) -> Self {
+            Self {
+                mode: job.mode,
+                scc_module: job.scc_module,
+                project_path: job.project,
+                cmake_lists: job.cmake_lists,
+                source_roots: job.source_roots,
+                entry_points: job.entry_points,
+                entry_points_file: job.entry_points_file,
+                strict_entry_points: job.strict_entry_points,
+                ad_hoc_entry_points: job.ad_hoc_entry_points,
+                changed_since: job.changed_since,
+                output_file_path: job.output,
+                format: job.format,
+                append: job.append,
+                split_by_endpoint: job.split_by_endpoint,
+                stats_only: job.stats_only,
+                min_cycle_length: job.min_cycle_length,
+                min_severity: job.min_severity,
+                suppressions_file: job.suppressions_file,
+                keep_template_impl_cycles: job.keep_template_impl_cycles,
+                max_allowed_cycles: job.max_allowed_cycles,
+                list_clean_files: job.list_clean_files,
+                show_include_lines: job.show_include_lines,
+                cycle_order: job.cycle_order,
+                keep_symlink_paths: job.keep_symlink_paths,
+                module_priority: job.module_priority,
+                module_aliases: job.module_aliases,
+                pair_units: job.pair_units,
+                ue_subfolders: job.ue_subfolders,
+                search_whole_project: job.search_whole_project,
+                diagnose_ambiguity: job.diagnose_ambiguity,
+                response_file: job.response_file,
+                scan_options: ScanOptions {
+                    include_cutoff: job.include_cutoff,
+                    open_retries: job.open_retries.unwrap_or(DEFAULT_OPEN_RETRIES),
+                    extensions: job.extensions,
+                },
+                traverse_options: TraverseOptions {
+                    allowed_file_types: job.allowed_file_types,
+                    root_sources_only: job.root_sources_only,
+                    excluded_modules: job.excluded_modules,
+                    only_module: job.only_module,
+                    pch_patterns: job.pch_patterns,
+                    skip_imports: job.skip_imports,
+                    abs_paths: job.abs_paths,
+                    fail_fast: job.fail_fast,
+                    minimal_cycles: job.minimal_cycles,
+                    max_cycles: match job.max_cycles {
+                        Some(0) => None,
+                        Some(max_cycles) => Some(max_cycles),
+                        None => Some(DEFAULT_MAX_CYCLES),
+                    },
+                    max_depth: job.max_depth,
+                },
+            }
+        }
+    }
+}
+
+pub mod settings {
+    use std::{fs, path::Path};
+
+    use anyhow::*;
+    use itertools::Itertools;
+    use serde::{Deserialize, Serialize};
+
+    use crate::{options::AnalysisOptions, report::ReportFormat};
+
+    pub const MAX_RECENT_PROJECTS: usize = 10;
+
+    // What the GUI remembers between runs, stored as TOML
+    #[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct Settings {
+        pub project_path: Option<String>,
+        pub entry_point: Option<String>,
+        pub output_file: Option<String>,
+        pub excluded_modules: Vec<String>,
+        // `None` picks the format from the output file extension
+        pub format: Option<ReportFormat>,
+        pub max_depth: Option<usize>,
+        pub keep_template_impl_cycles: bool,
+        // Settings of the latest runs, most recent first. Their own `recent` is always empty
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        pub recent: Vec<Settings>,
+    }
+
+    impl Settings {
+        // Makes the options of a run the current settings and puts them in front of `recent`,
+        // replacing an older run of the same project and entry point
+        pub fn remember(&mut self, options: &AnalysisOptions) {
+            let current = Self::from(options);
+
+            let mut recent = std::mem::take(&mut self.recent);
+            recent.retain(|run| {
+                run.project_path != current.project_path || run.entry_point != current.entry_point
+            });
+            recent.insert(0, current.clone());
+            recent.truncate(MAX_RECENT_PROJECTS);
+
+            *self = Self { recent, ..current };
+        }
+
+        // A missing file gives the defaults, one in the old line based format gets migrated
+        pub fn load(path: &str) -> Result<Self> {
+            let settings_path = Path::new(path);
+
+            if !settings_path.is_file() {
+                return Ok(Self::default());
+            }
+
+            let contents = fs::read_to_string(settings_path)
+                .with_context(|| format!("Couldn't read the settings: {}", path))?;
+
+            match toml::from_str(&contents) {
+                std::result::Result::Ok(settings) => Ok(settings),
+                Err(error) => {
+                    info!(
+                        "Settings aren't TOML ({}), migrating them from the line based format",
+                        error
+                    );
+
+                    let settings = Self::from_lines(&contents);
+                    settings.save(path)?;
+
+                    Ok(settings)
+                }
+            }
+        }
+
+        pub fn save(&self, path: &str) -> Result<()> {
+            fs::write(Path::new(path), toml::to_string(self)?)
+                .with_context(|| format!("Couldn't write the settings: {}", path))
+        }
+
+        // Project path, entry point, output file and comma separated excluded modules, one per
+        // line
+        fn from_lines(contents: &str) -> Self {
+            let mut lines = contents.lines().map(|line| line.trim().to_string());
+            let mut next_path = || lines.next().filter(|line| !line.is_empty());
+
+            Self {
+                project_path: next_path(),
+                entry_point: next_path(),
+                output_file: next_path(),
+                excluded_modules: next_path()
+                    .map(|excluded_modules| {
+                        excluded_modules
+                            .split(',')
+                            .map(|module| module.trim().to_string())
+                            .filter(|module| !module.is_empty())
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                ..Default::default()
+            }
+        }
+    }
+
+    impl From<&AnalysisOptions> for Settings {
+        fn from(options: &AnalysisOptions) -> Self {
+            Self {
+                project_path: Some(options.project_path.clone()),
+                entry_point: options.entry_points.first().cloned(),
+                output_file: Some(options.output_file_path.clone()),
+                excluded_modules: options
+                    .traverse_options
+                    .excluded_modules
+                    .iter()
+                    .sorted()
+                    .cloned()
+                    .collect(),
+                format: options.format,
+                max_depth: options.traverse_options.max_depth,
+                keep_template_impl_cycles: options.keep_template_impl_cycles,
+                recent: vec![],
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::test_fixture::Fixture;
+
+        #[test]
+        fn analysis_options_survive_a_save_and_load() {
+            let fixture = Fixture::empty();
+            let settings_path = fixture.path("settings.toml");
+            let options = AnalysisOptions::new("Project", "Project/Entry.h", "report.txt")
+                .format(ReportFormat::Json)
+                .max_depth(Some(8))
+                .keep_template_impl_cycles(true);
+
+            Settings::from(&options).save(&settings_path).unwrap();
+
+            let loaded = Settings::load(&settings_path).unwrap();
+            assert_eq!(loaded.format, Some(ReportFormat::Json));
+            assert_eq!(loaded.max_depth, Some(8));
+            assert!(loaded.keep_template_impl_cycles);
+        }
+    }
+}
+
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::mpsc::channel,
+    time::{Duration, Instant},
+};
+
+use anyhow::*;
+use itertools::Itertools;
+use notify::{DebouncedEvent, RecursiveMode, Watcher};
+
+use crate::{
+    file_info::{file_name_of, ScanOptions},
+    node::NodeTree,
+    options::{AnalysisMode, AnalysisOptions},
+    project::{Project, DEFAULT_CMAKE_LISTS},
+    report::{CycleReport, Findings, RecursivePaths, ReportFormat},
+    settings::Settings,
+};
+
+pub const CACHE_CONFIG_PATH: &str = "./.cache";
+
+// Total ordering over cycles: shorter ones first, ties broken by comparing the file names
+// element by element, so that the same tree always produces the same output
+pub fn cmp_paths(path1: &[String], path2: &[String]) -> Ordering {
+    Ord::cmp(&path1.len(), &path2.len()).then_with(|| Ord::cmp(path1, path2))
+}
+
+#[instrument(skip_all, fields(project_path = %options.project_path))]
+pub fn find_rec_deps(options: AnalysisOptions) -> Result<Findings> {
+    let findings = analyze_and_report(&options)?;
+
+    // A broken or unwritable settings file shouldn't fail a run that already succeeded
+    let mut settings = Settings::load(CACHE_CONFIG_PATH).unwrap_or_else(|error| {
+        warn!("Couldn't load the settings, starting over: {}", error);
+        Settings::default()
+    });
+    settings.remember(&options);
+    if let Err(error) = settings.save(CACHE_CONFIG_PATH) {
+        warn!("Couldn't save the settings: {:#}", error);
+    }
+
+    // Checked after the report is written, so the offending cycles can be looked up in it
+    if let Some(max_allowed_cycles) = options.max_allowed_cycles {
+        let distinct_cycles = findings.distinct_cycles();
+
+        if distinct_cycles > max_allowed_cycles {
+            bail!(
+                "Found {} distinct cycle(s), more than the {} allowed",
+                distinct_cycles,
+                max_allowed_cycles
+            );
+        }
+    }
+
+    Ok(findings)
+}
+
+// Cycles reachable from the entry points, without writing a report, for embedding the
+// analysis. Only for the entry points mode, see `find_rec_deps` for the cycle clusters
+pub fn analyze(options: &AnalysisOptions) -> Result<CycleReport> {
+    Ok(analyze_project(options)?.1)
+}
+
+// `analyze` along with the project, which the reports are written from. Every entry point
+// analysis goes through here so the written reports and the returned ones can't drift apart
+fn analyze_project(options: &AnalysisOptions) -> Result<(Project, CycleReport)> {
+    if options.mode != AnalysisMode::EntryPoints {
+        bail!("Only an entry point analysis produces a cycle report");
+    }
+
+    let mut project = create_project(options)?;
+    let cycle_report = find_entry_point_cycles(&mut project, options)?;
+
+    Ok((project, cycle_report))
+}
+
+// `find_rec_deps` from several entry points at once. The project and its parsed files are
+// shared between them, the recursive paths of every entry point are merged into one report
+pub fn find_rec_deps_multi(
+    project_path: &str,
+    entry_points: &[String],
+    output_file_path: &str,
+) -> Result<Findings> {
+    find_rec_deps(AnalysisOptions {
+        project_path: project_path.to_string(),
+        entry_points: entry_points.to_vec(),
+        output_file_path: output_file_path.to_string(),
+        ..Default::default()
+    })
+}
+
+// The recursive paths reachable from a single entry point with the default options, for tools
+// that only want the cycle data
+pub fn find_rec_deps_map(project_path: &str, entry_point: &str) -> Result<RecursivePaths> {
+    Ok(analyze(&AnalysisOptions::new(project_path, entry_point, ""))?.into_recursive_paths())
+}
+
+// `analyze` on tokio's blocking thread pool, so a big analysis doesn't stall the executor. The
+// project is `Rc` based and stays on that thread, only the report comes back
+#[cfg(feature = "async")]
+pub async fn analyze_async(options: AnalysisOptions) -> Result<CycleReport> {
+    tokio::task::spawn_blocking(move || analyze(&options)).await?
+}
+
+// `find_rec_deps` without touching the settings
+fn analyze_and_report(options: &AnalysisOptions) -> Result<Findings> {
+    let format = options
+        .format
+        .unwrap_or_else(|| ReportFormat::from_path(&options.output_file_path));
+
+    if options.stats_only && format == ReportFormat::Mermaid {
+        bail!("A Mermaid diagram can't hold only the stats");
+    }
+
+    if options.list_clean_files && (format != ReportFormat::Text || options.stats_only) {
+        bail!("Only full text reports can list the files in no cycle");
+    }
+
+    if options.show_include_lines && (format != ReportFormat::Text || options.stats_only) {
+        bail!("Only full text reports can show the include lines of the cycles");
+    }
+
+    if options.diagnose_ambiguity && format != ReportFormat::Text {
+        bail!("Only text reports can list the ambiguous includes");
+    }
+
+    if options.split_by_endpoint {
+        if options.mode == AnalysisMode::Scc {
+            bail!("Cycle clusters have no endpoints to split the report by");
+        }
+        if options
+            .format
+            .is_some_and(|format| format != ReportFormat::Text)
+        {
+            bail!("Only text reports can be split by endpoint");
+        }
+        if options.append || options.stats_only {
+            bail!("A report split by endpoint can't be appended to or hold only the stats");
+        }
+    }
+
+    let (project, findings) = match options.mode {
+        AnalysisMode::EntryPoints => {
+            let (project, cycle_report) = analyze_project(options)?;
+            write_cycle_report(&project, options, format, &cycle_report)?;
+
+            (project, Findings::Cycles(cycle_report))
+        }
+        AnalysisMode::Scc => {
+            let mut project = create_project(options)?;
+            let clusters = find_cycle_clusters(&mut project, options, format)?;
+
+            (project, Findings::Clusters(clusters))
+        }
+    };
+
+    info!("Metrics:\n{}", project.metrics);
+
+    Ok(findings)
+}
+
+fn create_project(options: &AnalysisOptions) -> Result<Project> {
+    let project_parsing_start = Instant::now();
+    let mut project = Project::create_with_scan_options(
+        &options.project_path,
+        options.cmake_lists.as_deref(),
+        &options.source_roots,
+        &options.scan_options,
+    )?;
+    project.metrics.project_parsing = project_parsing_start.elapsed();
+    project.keep_symlink_paths = options.keep_symlink_paths;
+    project.module_priority = options.module_priority.clone();
+    project.module_aliases = options.module_aliases.clone();
+    project.pair_units = options.pair_units;
+    project.ue_subfolders = options.ue_subfolders;
+    project.diagnose_ambiguity = options.diagnose_ambiguity;
+
+    if let Some(response_file) = &options.response_file {
+        project.add_response_file(response_file)?;
+    }
+
+    // After the response file, the project root is the last resort
+    if options.search_whole_project {
+        project.enable_whole_project_search();
+    }
+
+    for (alias, module) in options.module_aliases.iter() {
+        if !project
+            .modules
+            .iter()
+            .any(|(modl, _include_paths)| modl == module)
+        {
+            warn!(
+                "Alias '{}' is for module '{}' which doesn't exist",
+                alias, module
+            );
+        }
+    }
+
+    Ok(project)
+}
+
+fn find_entry_point_cycles(
+    project: &mut Project,
+    options: &AnalysisOptions,
+) -> Result<CycleReport> {
+    let mut recursive_paths: RecursivePaths = HashMap::new();
+    let mut traverse_options = options.traverse_options.clone();
+    traverse_options.excluded_modules = options
+        .traverse_options
+        .excluded_modules
+        .iter()
+        .map(|module| project.module_name(module).to_string())
+        .collect();
+    traverse_options.only_module = options
+        .traverse_options
+        .only_module
+        .as_deref()
+        .map(|module| project.module_name(module).to_string());
+    let mut truncated = false;
+
+    let changed_files = match &options.changed_since {
+        Some(base_ref) => Some(changed_files(
+            &options.project_path,
+            base_ref,
+            &options.scan_options,
+        )?),
+        None => None,
+    };
+
+    let mut entry_points = options.entry_points.clone();
+    if let Some(entry_points_file) = &options.entry_points_file {
+        entry_points.extend(read_entry_points_file(entry_points_file)?);
+    }
+    if options.ad_hoc_entry_points {
+        for entry_point in entry_points.iter() {
+            // An entry point that can't be opened is reported below
+            project.add_ad_hoc_module(entry_point).ok();
+        }
+    }
+
+    if let Some(changed_files) = &changed_files {
+        info!("{} supported file(s) changed", changed_files.len());
+
+        entry_points.extend(changed_files.iter().cloned());
+    }
+
+    let mut skipped_entry_points = 0;
+
+    for entry_point in entry_points.iter() {
+        let entry_point_file_info = match project.entry_file_info(entry_point) {
+            std::result::Result::Ok(file_info) => file_info,
+            Err(err) if !options.strict_entry_points => {
+                warn!("Skipping the entry point {}: {:#}", entry_point, err);
+                skipped_entry_points += 1;
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+
+        let mut node_tree = NodeTree::create(&entry_point_file_info);
+
+        // The `max_cycles` limit is shared between all the entry points
+        let (traversal_start, file_parsing) = (Instant::now(), project.metrics.file_parsing);
+        truncated |= node_tree.traverse_into(project, &traverse_options, &mut recursive_paths);
+        project.metrics.traversal += traversal_start
+            .elapsed()
+            .saturating_sub(project.metrics.file_parsing - file_parsing);
+
+        if options.traverse_options.fail_fast && !recursive_paths.is_empty() {
+            break;
+        }
+
+        if truncated {
+            break;
+        }
+    }
+
+    if !entry_points.is_empty() && skipped_entry_points == entry_points.len() {
+        bail!(
+            "None of the {} entry point(s) could be opened",
+            entry_points.len()
+        );
+    }
+
+    // Before the other filters, so a suppression is only stale if its cycle is really gone
+    let mut suppressed_cycles = 0;
+    let mut stale_suppressions = vec![];
+
+    if let Some(suppressions_file) = &options.suppressions_file {
+        let suppressions = report::read_suppressions(suppressions_file)?;
+        let cycles = report::canonical_cycles(&recursive_paths);
+
+        for paths in recursive_paths.values_mut() {
+            paths.retain(|path| !suppressions.contains(&report::canonical_cycle(path)));
+        }
+        recursive_paths.retain(|_file_name, paths| !paths.is_empty());
+
+        suppressed_cycles = cycles.intersection(&suppressions).count();
+        stale_suppressions = suppressions
+            .difference(&cycles)
+            .sorted_by(|cycle1, cycle2| cmp_paths(cycle1, cycle2))
+            .map(|cycle| report::format_cycle(cycle))
+            .collect();
+
+        info!("{} known cycle(s) suppressed", suppressed_cycles);
+        for stale_suppression in stale_suppressions.iter() {
+            warn!(
+                "Stale suppression, the cycle is gone: {}",
+                stale_suppression
+            );
+        }
+    }
+
+    let mut template_impl_cycles = 0;
+
+    if !options.keep_template_impl_cycles {
+        let file_types = report::file_types_by_file(&project.files);
+
+        template_impl_cycles = report::canonical_cycles(&recursive_paths)
+            .iter()
+            .filter(|cycle| report::is_template_impl_cycle(cycle, &file_types))
+            .count();
+
+        for paths in recursive_paths.values_mut() {
+            paths.retain(|path| {
+                !report::is_template_impl_cycle(&report::canonical_cycle(path), &file_types)
+            });
+        }
+        recursive_paths.retain(|_file_name, paths| !paths.is_empty());
+    }
+
+    if let Some(changed_files) = changed_files {
+        let changed_file_names: HashSet<String> = changed_files
+            .iter()
+            .filter_map(|changed_file| Path::new(changed_file).file_name())
+            .map(|file_name| file_name.to_string_lossy().to_string())
+            .collect();
+
+        // Only the cycle itself counts, not the part of the path leading to it. Only file names
+        // are compared, so a same-named unchanged file can keep a cycle in as well
+        for paths in recursive_paths.values_mut() {
+            paths.retain(|path| {
+                report::canonical_cycle(path)
+                    .iter()
+                    .any(|file| changed_file_names.contains(file_name_of(file)))
+            });
+        }
+        recursive_paths.retain(|_file_name, paths| !paths.is_empty());
+    }
+
+    if let Some(min_cycle_length) = options.min_cycle_length {
+        for paths in recursive_paths.values_mut() {
+            paths.retain(|path| report::canonical_cycle(path).len() >= min_cycle_length);
+        }
+        recursive_paths.retain(|_file_name, paths| !paths.is_empty());
+    }
+
+    if let Some(min_severity) = options.min_severity {
+        let file_infos = report::file_infos_by_file(&project.files);
+
+        for paths in recursive_paths.values_mut() {
+            paths.retain(|path| {
+                report::cycle_severity(&report::canonical_cycle(path), &file_infos) >= min_severity
+            });
+        }
+        recursive_paths.retain(|_file_name, paths| !paths.is_empty());
+    }
+
+    let mut cycle_report = CycleReport::new(recursive_paths, &project.files);
+    cycle_report.truncated = truncated;
+    cycle_report.suppressed_cycles = suppressed_cycles;
+    cycle_report.stale_suppressions = stale_suppressions;
+    cycle_report.template_impl_cycles = template_impl_cycles;
+
+    Ok(cycle_report)
+}
+
+fn write_cycle_report(
+    project: &Project,
+    options: &AnalysisOptions,
+    format: ReportFormat,
+    cycle_report: &CycleReport,
+) -> Result<()> {
+    let recursive_paths = cycle_report.recursive_paths();
+
+    if options.split_by_endpoint {
+        report::write_split_text(&options.output_file_path, recursive_paths)?;
+
+        return Ok(());
+    }
+
+    let mut file = open_output_file(options, format)?;
+
+    match format {
+        ReportFormat::Text => {
+            if options.traverse_options.fail_fast && !recursive_paths.is_empty() {
+                file.write_all(b"Stopped at the first cycle found (fail fast)\n")?;
+            }
+
+            if cycle_report.truncated {
+                file.write_all(
+                    format!(
+                        "Reached the limit of {} recursive paths, the report is truncated\n",
+                        options.traverse_options.max_cycles.unwrap()
+                    )
+                    .as_bytes(),
+                )?;
+            }
+
+            if cycle_report.suppressed_cycles > 0 {
+                file.write_all(
+                    format!(
+                        "Suppressed {} known cycle(s)\n",
+                        cycle_report.suppressed_cycles
+                    )
+                    .as_bytes(),
+                )?;
+            }
+
+            for stale_suppression in cycle_report.stale_suppressions.iter() {
+                file.write_all(
+                    format!(
+                        "Stale suppression (no longer matches any cycle): {}\n",
+                        stale_suppression
+                    )
+                    .as_bytes(),
+                )?;
+            }
+
+            if cycle_report.template_impl_cycles > 0 {
+                file.write_all(
+                    format!(
+                        "Left out {} template impl cycle(s) (a header including its own \
+                         implementation file)\n",
+                        cycle_report.template_impl_cycles
+                    )
+                    .as_bytes(),
+                )?;
+            }
+
+            if options.stats_only {
+                report::write_summary_text(
+                    &mut file,
+                    recursive_paths,
+                    &project.files,
+                    &project.metrics,
+                )?
+            } else {
+                report::write_text(
+                    &mut file,
+                    recursive_paths,
+                    &project.files,
+                    &project.metrics,
+                    options.cycle_order,
+                )?
+            }
+
+            if options.show_include_lines {
+                report::write_include_edges_text(&mut file, recursive_paths, &project.files)?;
+            }
+
+            if options.list_clean_files {
+                report::write_clean_files_text(&mut file, recursive_paths, &project.files)?;
+            }
+
+            if options.diagnose_ambiguity {
+                report::write_ambiguous_includes_text(&mut file, &project.ambiguous_includes)?;
+            }
+        }
+        ReportFormat::Json if options.stats_only => report::write_summary_json(
+            &mut file,
+            recursive_paths,
+            &project.files,
+            &project.metrics,
+            cycle_report.truncated,
+        )?,
+        ReportFormat::Json => report::write_json(
+            &mut file,
+            recursive_paths,
+            &project.files,
+            &project.metrics,
+            cycle_report.truncated,
+        )?,
+        ReportFormat::Mermaid => report::write_mermaid(&mut file, recursive_paths)?,
+    }
+
+    Ok(())
+}
+
+fn read_entry_points_file(entry_points_file: &str) -> Result<Vec<String>> {
+    let contents = fs::read_to_string(Path::new(entry_points_file))
+        .with_context(|| format!("Couldn't read the entry points file: {}", entry_points_file))?;
+
+    let mut entry_points = vec![];
+
+    for (line_ind, line) in contents.lines().enumerate() {
+        let entry_point = line.trim();
+
+        if entry_point.is_empty() || entry_point.starts_with('#') {
+            continue;
+        }
+
+        // Checked up front, a typo shouldn't surface only after the other entry points ran
+        if !Path::new(entry_point).is_file() {
+            bail!(
+                "Entry point on line {} of {} doesn't exist: {}",
+                line_ind + 1,
+                entry_points_file,
+                entry_point
+            );
+        }
+
+        entry_points.push(entry_point.to_string());
+    }
+
+    Ok(entry_points)
+}
+
+// Existing files of supported types that differ from `base_ref`, including uncommitted changes
+fn changed_files(
+    project_path: &str,
+    base_ref: &str,
+    scan_options: &ScanOptions,
+) -> Result<Vec<String>> {
+    let output = Command::new("git")
+        .args([
+            "-C",
+            project_path,
+            "diff",
+            "--name-only",
+            "--relative",
+            base_ref,
+        ])
+        .output()
+        .context("Couldn't run git")?;
+
+    if !output.status.success() {
+        bail!(
+            "git diff against '{}' failed: {}",
+            base_ref,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|changed_file| Path::new(project_path).join(changed_file))
+        // Deleted files are part of the diff too
+        .filter(|changed_file| changed_file.is_file())
+        .filter(|changed_file| {
+            changed_file
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .and_then(|extension| scan_options.file_type(extension))
+                .is_some()
+        })
+        .map(|changed_file| changed_file.to_string_lossy().to_string())
+        .collect())
+}
+
+fn find_cycle_clusters(
+    project: &mut Project,
+    options: &AnalysisOptions,
+    format: ReportFormat,
+) -> Result<Vec<Vec<String>>> {
+    // Clusters don't keep the edges between their files
+    if format == ReportFormat::Mermaid {
+        bail!("Cycle clusters can't be written as a Mermaid diagram");
+    }
+
+    project.add_module_files(options.scc_module.as_deref())?;
+
+    let (traversal_start, file_parsing) = (Instant::now(), project.metrics.file_parsing);
+    let clusters = project.cycle_clusters();
+    project.metrics.traversal = traversal_start
+        .elapsed()
+        .saturating_sub(project.metrics.file_parsing - file_parsing);
+
+    let mut file = open_output_file(options, format)?;
+
+    match format {
+        ReportFormat::Text => {
+            report::write_clusters_text(&mut file, &clusters, &project.metrics)?;
+
+            if options.diagnose_ambiguity {
+                report::write_ambiguous_includes_text(&mut file, &project.ambiguous_includes)?;
+            }
+        }
+        ReportFormat::Json => report::write_clusters_json(&mut file, &clusters, &project.metrics)?,
+        ReportFormat::Mermaid => unreachable!(),
+    }
+
+    Ok(clusters
+        .iter()
+        .map(|cluster| {
+            cluster
+                .iter()
+                .map(|file_info| (*file_info).borrow().file_name.to_string())
+                .collect()
+        })
+        .collect())
+}
+
+fn open_output_file(options: &AnalysisOptions, format: ReportFormat) -> Result<File> {
+    // Output paths typed into a fresh folder are common on the first run
+    if let Some(parent) = Path::new(&options.output_file_path).parent() {
+        if !parent.as_os_str().is_empty() && !parent.exists() {
+            fs::create_dir_all(parent).with_context(|| {
+                format!(
+                    "The output directory {} doesn't exist and couldn't be created, create it \
+                     first",
+                    parent.display()
+                )
+            })?;
+        }
+    }
+
+    if !options.append {
+        return File::create(Path::new(&options.output_file_path)).with_context(|| {
+            format!(
+                "Couldn't create the output file {}",
+                options.output_file_path
+            )
+        });
+    }
+
+    match format {
+        ReportFormat::Text => {}
+        ReportFormat::Json => {
+            bail!("JSON reports can't be appended to, they would stop being valid JSON")
+        }
+        ReportFormat::Mermaid => {
+            bail!("Mermaid reports can't be appended to, a file holds a single diagram")
+        }
+    }
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(Path::new(&options.output_file_path))?;
+
+    let header = match options.mode {
+        AnalysisMode::EntryPoints => format!("Entry points: {}", options.entry_points.join(", ")),
+        AnalysisMode::Scc => format!(
+            "Cycle clusters of: {}",
+            options.scc_module.as_deref().unwrap_or("whole project")
+        ),
+    };
+
+    file.write_all(b"################################################\n")?;
+    file.write_all(format!("{}\n", header).as_bytes())?;
+    file.write_all(b"################################################\n")?;
+
+    Ok(file)
+}
+
+// Runs the whole pipeline (CMake parsing, module detection, file parsing and traversal) on a
+// generated project with known cycles, returns whether exactly those cycles were found
+pub fn self_test() -> Result<bool> {
+    let project_dir = std::env::temp_dir().join(format!(
+        "ue_rec_deps_seeker_self_test_{}",
+        std::process::id()
+    ));
+    let result = run_self_test(&project_dir);
+
+    if let Err(error) = fs::remove_dir_all(&project_dir) {
+        warn!(
+            "Couldn't remove {}: {}",
+            project_dir.to_string_lossy(),
+            error
+        );
+    }
+
+    result
+}
+
+fn run_self_test(project_dir: &Path) -> Result<bool> {
+    let public_dir = project_dir.join("Engine/Source/Runtime/SelfTest/Public");
+    fs::create_dir_all(&public_dir)?;
+
+    fs::write(
+        project_dir.join(DEFAULT_CMAKE_LISTS),
+        "include(\"${CMAKE_CURRENT_SOURCE_DIR}/self-test-includes.cmake\")\n",
+    )?;
+    fs::write(
+        project_dir.join("self-test-includes.cmake"),
+        format!(
+            "set(SELF_TEST_INCLUDES\n\t\"{}\"\n)\n",
+            public_dir.to_string_lossy()
+        ),
+    )?;
+
+    fs::write(
+        public_dir.join("SelfTestA.h"),
+        "#pragma once\n#include \"SelfTestB.h\"\n#include \"SelfTestA.generated.h\"\n",
+    )?;
+    fs::write(
+        public_dir.join("SelfTestB.h"),
+        "#pragma once\n#include \"SelfTestA.h\"\n#include \"SelfTestC.h\"\n",
+    )?;
+    fs::write(
+        public_dir.join("SelfTestC.h"),
+        "#pragma once\n#include \"SelfTestC.h\"\n",
+    )?;
+
+    let options = AnalysisOptions::new(
+        &project_dir.to_string_lossy(),
+        &public_dir.join("SelfTestA.h").to_string_lossy(),
+        &project_dir.join("self-test-report.txt").to_string_lossy(),
+    );
+
+    let expected_paths = [
+        (
+            "SelfTestA.h",
+            vec!["SelfTestA.h", "SelfTestB.h", "SelfTestA.h"],
+        ),
+        // Self include
+        (
+            "SelfTestC.h",
+            vec!["SelfTestA.h", "SelfTestB.h", "SelfTestC.h", "SelfTestC.h"],
+        ),
+    ];
+
+    Ok(match analyze_and_report(&options)? {
+        Findings::Cycles(cycle_report) => {
+            let recursive_paths = cycle_report.recursive_paths();
+
+            recursive_paths.len() == expected_paths.len()
+                && expected_paths.iter().all(|(key, expected_path)| {
+                    let expected_path: Vec<String> =
+                        expected_path.iter().map(|file| file.to_string()).collect();
+
+                    recursive_paths
+                        .get(*key)
+                        .is_some_and(|paths| paths.len() == 1 && paths.contains(&expected_path))
+                })
+        }
+        Findings::Clusters(_) => false,
+    })
+}
+
+// Runs `find_rec_deps` again whenever a file of a supported type changes under one of the
+// project's include paths, until the watcher fails. `on_run` gets every new result along with
+// the previous one, a failed rerun is only logged since the files are likely mid-edit
+pub fn watch(
+    options: AnalysisOptions,
+    mut on_run: impl FnMut(&CycleReport, Option<&CycleReport>),
+) -> Result<()> {
+    if options.mode != AnalysisMode::EntryPoints {
+        bail!("Only an entry point analysis can be watched");
+    }
+
+    let project = Project::create_with_scan_options(
+        &options.project_path,
+        options.cmake_lists.as_deref(),
+        &options.source_roots,
+        &options.scan_options,
+    )?;
+
+    let (sender, receiver) = channel();
+    let mut watcher = notify::watcher(sender, Duration::from_millis(500))?;
+
+    // Nested include paths would be watched twice otherwise
+    let include_roots = project
+        .modules
+        .iter()
+        .flat_map(|(_modl, include_paths)| include_paths.iter().map(PathBuf::from))
+        .filter(|include_path| include_path.is_dir())
+        .sorted()
+        .dedup()
+        .collect::<Vec<_>>();
+
+    for include_root in include_roots.iter() {
+        if include_roots
+            .iter()
+            .any(|other| other != include_root && include_root.starts_with(other))
+        {
+            continue;
+        }
+
+        watcher.watch(include_root, RecursiveMode::Recursive)?;
+    }
+
+    info!("Watching {} include path(s)", include_roots.len());
+
+    let is_relevant = |path: &Path| {
+        path.extension()
+            .and_then(|extension| options.scan_options.file_type(&extension.to_string_lossy()))
+            .is_some()
+    };
+
+    let mut last_report = None;
+
+    loop {
+        match find_rec_deps(options.clone()) {
+            std::result::Result::Ok(Findings::Cycles(cycle_report)) => {
+                on_run(&cycle_report, last_report.as_ref());
+                last_report = Some(cycle_report);
+            }
+            std::result::Result::Ok(Findings::Clusters(_)) => unreachable!(),
+            Err(err) => warn!("The analysis failed, waiting for more changes: {:#}", err),
+        }
+
+        // Saving in an editor usually fires a few events, they're all handled by one run
+        loop {
+            let changed_paths = match receiver.recv()? {
+                DebouncedEvent::Create(path)
+                | DebouncedEvent::Write(path)
+                | DebouncedEvent::Remove(path) => vec![path],
+                DebouncedEvent::Rename(from, to) => vec![from, to],
+                DebouncedEvent::Error(error, path) => {
+                    warn!("Watcher error ({:?}): {}", path, error);
+                    vec![]
+                }
+                _ => vec![],
+            };
+
+            if changed_paths.iter().any(|path| is_relevant(path)) {
+                debug!("Changed: {:?}", changed_paths);
+                break;
+            }
+        }
+
+        while receiver.try_recv().is_ok() {}
+    }
+}
+
+pub fn run_manifest(manifest_path: &str) -> Result<()> {
+    for options in AnalysisOptions::from_manifest(manifest_path)? {
+        info!("Running the analysis for {}", options.output_file_path);
+
+        find_rec_deps(options)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::Itertools;
+
+    use crate::{
+        node::TraverseOptions,
+        test_fixture::{write_mutual_pairs, Fixture},
+    };
+
+    // Which `Shared.h` the entry point gets depends on the order of the equally long modules
+    #[test]
+    fn two_runs_find_identical_paths() {
+        let fixture = Fixture::new(&[
+            "Engine/Source/Runtime/Aaa",
+            "Engine/Source/Runtime/Bbb",
+            "Engine/Source/Runtime/Ccc",
+        ]);
+        let entry_point = fixture.write(
+            "Engine/Source/Runtime/Ccc/Public/Entry.h",
+            "#include \"Shared.h\"\n",
+        );
+        fixture.write(
+            "Engine/Source/Runtime/Aaa/Public/Shared.h",
+            "#include \"FromAaa.h\"\n",
+        );
+        fixture.write(
+            "Engine/Source/Runtime/Bbb/Public/Shared.h",
+            "#include \"FromBbb.h\"\n",
+        );
+        fixture.write(
+            "Engine/Source/Runtime/Ccc/Public/FromAaa.h",
+            "#include \"Entry.h\"\n",
+        );
+        fixture.write(
+            "Engine/Source/Runtime/Ccc/Public/FromBbb.h",
+            "#include \"Entry.h\"\n",
+        );
+
+        let recursive_paths = || {
+            let mut project = Project::create(fixture.root()).unwrap();
+            let entry_point_file_info = project.create_file_info(&entry_point).unwrap();
+            let mut node_tree = NodeTree::create(&entry_point_file_info);
+
+            node_tree.traverse(&mut project, &TraverseOptions::default())
+        };
+
+        let first_paths = recursive_paths();
+        for _ in 0..5 {
+            assert_eq!(recursive_paths(), first_paths);
+        }
+    }
+
+    // Two mutual pairs, the limit lets only one of them in
+    #[test]
+    fn json_report_says_when_it_was_truncated() {
+        let fixture = Fixture::new(&["Engine/Source/Runtime/Core"]);
+        let public = |file_name: &str| format!("Engine/Source/Runtime/Core/Public/{}", file_name);
+        let entry_point = fixture.write(&public("Entry.h"), "#include \"A.h\"\n#include \"C.h\"\n");
+        fixture.write(&public("A.h"), "#include \"B.h\"\n");
+        fixture.write(&public("B.h"), "#include \"A.h\"\n");
+        fixture.write(&public("C.h"), "#include \"D.h\"\n");
+        fixture.write(&public("D.h"), "#include \"C.h\"\n");
+        let output_file = fixture.path("report.json");
+
+        analyze_and_report(
+            &AnalysisOptions::new(fixture.root(), &entry_point, &output_file).max_cycles(Some(1)),
+        )
+        .unwrap();
+
+        let report: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&output_file).unwrap()).unwrap();
+        assert_eq!(report["summary"]["truncated"], serde_json::json!(true));
+    }
+
+    // A 2 file and a 3 file cycle
+    #[test]
+    fn min_cycle_length_keeps_cycles_of_exactly_that_length() {
+        let fixture = Fixture::new(&["Engine/Source/Runtime/Core"]);
+        let public = |file_name: &str| format!("Engine/Source/Runtime/Core/Public/{}", file_name);
+        let entry_point = fixture.write(&public("Entry.h"), "#include \"A.h\"\n#include \"C.h\"\n");
+        fixture.write(&public("A.h"), "#include \"B.h\"\n");
+        fixture.write(&public("B.h"), "#include \"A.h\"\n");
+        fixture.write(&public("C.h"), "#include \"D.h\"\n");
+        fixture.write(&public("D.h"), "#include \"E.h\"\n");
+        fixture.write(&public("E.h"), "#include \"C.h\"\n");
+        let cycle_keys = |min_cycle_length: usize| {
+            let options = AnalysisOptions::new(fixture.root(), &entry_point, "")
+                .min_cycle_length(min_cycle_length);
+
+            analyze(&options)
+                .unwrap()
+                .recursive_paths()
+                .keys()
+                .cloned()
+                .sorted()
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(cycle_keys(2), ["A.h", "C.h"]);
+        assert_eq!(cycle_keys(3), ["C.h"]);
+        assert!(cycle_keys(4).is_empty());
+    }
+
+    // `Foo.hpp`<->`Foo_impl.cpp` is the template pattern, `Bar.h`<->`Baz.cpp` a real cycle
+    #[test]
+    fn template_impl_cycles_are_left_out_unless_kept() {
+        let fixture = Fixture::new(&["Engine/Source/Runtime/Core"]);
+        let public = |file_name: &str| format!("Engine/Source/Runtime/Core/Public/{}", file_name);
+        let entry_point = fixture.write(
+            &public("Entry.h"),
+            "#include \"Foo.hpp\"\n#include \"Bar.h\"\n",
+        );
+        fixture.write(&public("Foo.hpp"), "#include \"Foo_impl.cpp\"\n");
+        fixture.write(&public("Foo_impl.cpp"), "#include \"Foo.hpp\"\n");
+        fixture.write(&public("Bar.h"), "#include \"Baz.cpp\"\n");
+        fixture.write(&public("Baz.cpp"), "#include \"Bar.h\"\n");
+        let options = AnalysisOptions::new(fixture.root(), &entry_point, "");
+
+        let report = analyze(&options).unwrap();
+        assert_eq!(report.template_impl_cycles, 1);
+        assert_eq!(
+            report.recursive_paths().keys().collect::<Vec<_>>(),
+            ["Bar.h"]
+        );
+
+        let report = analyze(&options.clone().keep_template_impl_cycles(true)).unwrap();
+        assert_eq!(report.template_impl_cycles, 0);
+        assert_eq!(report.recursive_paths().len(), 2);
+    }
+
+    // `Scratch` is on no include path, its own directory resolves `Bar.h`
+    #[test]
+    fn unregistered_entry_header_gets_an_ad_hoc_module() {
+        let fixture = Fixture::new(&["Engine/Source/Runtime/Core"]);
+        let entry_point = fixture.write("Scratch/Foo.h", "#include \"Bar.h\"\n");
+        fixture.write("Scratch/Bar.h", "#include \"Foo.h\"\n");
+        let options = AnalysisOptions::new(fixture.root(), &entry_point, "");
+
+        assert!(analyze(&options).is_err());
+
+        let report = analyze(&AnalysisOptions {
+            ad_hoc_entry_points: true,
+            ..options
+        })
+        .unwrap();
+
+        assert_eq!(
+            report.recursive_paths()["Foo.h"],
+            HashSet::from([vec![
+                "Foo.h".to_string(),
+                "Bar.h".to_string(),
+                "Foo.h".to_string()
+            ]])
+        );
+    }
+
+    #[test]
+    fn output_file_in_a_missing_nested_directory_is_created() {
+        let fixture = Fixture::new(&["Engine/Source/Runtime/Core"]);
+        let entry_point = fixture.write("Engine/Source/Runtime/Core/Public/Clean.h", "");
+        let output_file = fixture.path("Reports/Nested/report.txt");
+
+        analyze_and_report(&AnalysisOptions::new(
+            fixture.root(),
+            &entry_point,
+            &output_file,
+        ))
+        .unwrap();
+
+        assert!(Path::new(&output_file).is_file());
+    }
+
+    #[test]
+    fn find_rec_deps_map_matches_the_written_report() {
+        let fixture = Fixture::new(&["Engine/Source/Runtime/Core"]);
+        let entry_point = write_mutual_pairs(&fixture, "Entry", 3);
+        let output_file = fixture.path("report.json");
+
+        analyze_and_report(&AnalysisOptions::new(
+            fixture.root(),
+            &entry_point,
+            &output_file,
+        ))
+        .unwrap();
+
+        assert_eq!(
+            find_rec_deps_map(fixture.root(), &entry_point).unwrap(),
+            report::read_json(&output_file).unwrap()
+        );
+    }
+
+    // Each entry point has a cycle of its own, both include the clean `Shared.h`
+    #[test]
+    fn entry_points_share_parsed_files_and_merge_their_paths() {
+        let fixture = Fixture::new(&["Engine/Source/Runtime/Core"]);
+        let public = |file_name: &str| format!("Engine/Source/Runtime/Core/Public/{}", file_name);
+        let first = fixture.write(
+            &public("First.h"),
+            "#include \"Shared.h\"\n#include \"A.h\"\n",
+        );
+        let second = fixture.write(
+            &public("Second.h"),
+            "#include \"Shared.h\"\n#include \"B.h\"\n",
+        );
+        fixture.write(&public("Shared.h"), "");
+        fixture.write(&public("A.h"), "#include \"First.h\"\n");
+        fixture.write(&public("B.h"), "#include \"Second.h\"\n");
+
+        let run = |entry_points: &[&String]| {
+            analyze_project(&AnalysisOptions {
+                project_path: fixture.root().to_string(),
+                entry_points: entry_points
+                    .iter()
+                    .map(|entry_point| entry_point.to_string())
+                    .collect(),
+                ..Default::default()
+            })
+            .unwrap()
+        };
+
+        let (first_project, first_report) = run(&[&first]);
+        let (second_project, second_report) = run(&[&second]);
+        let (project, report) = run(&[&first, &second]);
+
+        assert_eq!(
+            project
+                .files
+                .iter()
+                .filter(|file_info| &*(**file_info).borrow().file_name == "Shared.h")
+                .count(),
+            1
+        );
+        assert_eq!(
+            project.metrics.files_parsed,
+            first_project.metrics.files_parsed + second_project.metrics.files_parsed - 1
+        );
+
+        let mut union = first_report.recursive_paths().clone();
+        for (file_name, paths) in second_report.recursive_paths() {
+            union
+                .entry(file_name.clone())
+                .or_default()
+                .extend(paths.iter().cloned());
+        }
+
+        assert_eq!(report.recursive_paths(), &union);
+        assert_eq!(union.values().map(|paths| paths.len()).sum::<usize>(), 2);
+    }
+}
+
+#[cfg(test)]
+mod test_fixture {
+    use std::{fs, path::Path};
+
+    use tempfile::TempDir;
+
+    // A UE-like project in a temporary directory, `new` lists the `Public` folder of every module
+    // in its `CMakeLists.txt`
+    pub struct Fixture {
+        dir: TempDir,
+    }
+
+    impl Fixture {
+        pub fn new(modules: &[&str]) -> Self {
+            let fixture = Self {
+                dir: TempDir::new().unwrap(),
+            };
+
+            let include_folders = modules
+                .iter()
+                .map(|module| {
+                    let public_folder = fixture.path(&format!("{}/Public", module));
+                    fs::create_dir_all(&public_folder).unwrap();
+
+                    format!("\t\"{}\"\n", public_folder)
+                })
+                .collect::<String>();
+
+            let cmake_includes = fixture.write(
+                "cmake-includes.cmake",
+                &format!("set(INCLUDE_DIRECTORIES\n{})\n", include_folders),
+            );
+            fixture.write(
+                "CMakeLists.txt",
+                &format!("include(\"{}\")\n", cmake_includes),
+            );
+
+            fixture
+        }
+
+        // Without any cmake file
+        pub fn empty() -> Self {
+            Self {
+                dir: TempDir::new().unwrap(),
+            }
+        }
+
+        pub fn root(&self) -> &str {
+            self.dir.path().to_str().unwrap()
+        }
+
+        pub fn path(&self, rel_path: &str) -> String {
+            self.dir.path().join(rel_path).to_string_lossy().to_string()
+        }
+
+        // Creates the parent folders as needed, returns the absolute path
+        pub fn write(&self, rel_path: &str, contents: &str) -> String {
+            self.write_bytes(rel_path, contents.as_bytes())
+        }
+
+        pub fn write_bytes(&self, rel_path: &str, contents: &[u8]) -> String {
+            let path = self.path(rel_path);
+
+            if let Some(parent) = Path::new(&path).parent() {
+                fs::create_dir_all(parent).unwrap();
+            }
+            fs::write(&path, contents).unwrap();
+
+            path
+        }
+    }
+
+    // `{entry}.h` including `count` headers, each in a mutual pair with another one
+    pub fn write_mutual_pairs(fixture: &Fixture, entry: &str, count: usize) -> String {
+        let public_dir = "Engine/Source/Runtime/Core/Public";
+
+        for ind in 0..count {
+            fixture.write(
+                &format!("{}/{}Left{}.h", public_dir, entry, ind),
+                &format!("#include \"{}Right{}.h\"\n", entry, ind),
+            );
+            fixture.write(
+                &format!("{}/{}Right{}.h", public_dir, entry, ind),
+                &format!("#include \"{}Left{}.h\"\n", entry, ind),
+            );
+        }
+
+        fixture.write(
+            &format!("{}/{}.h", public_dir, entry),
+            &(0..count)
+                .map(|ind| format!("#include \"{}Left{}.h\"\n", entry, ind))
+                .collect::<String>(),
+        )
+    }
 }
@@ -1,19 +1,26 @@
 #[macro_use]
-extern crate log;
+extern crate tracing;
 
-use std::{
-    fs::File,
-    io::{BufRead, BufReader},
-    path::Path,
-};
+use std::{collections::HashSet, fs, path::Path, thread};
 
-use gtk::{glib::Sender, prelude::*};
+use gtk::{gdk::DragAction, gio, glib::Sender, prelude::*};
 use native_dialog::{FileDialog, MessageDialog, MessageType};
 use relm4::{send, AppUpdate, Model, RelmApp, WidgetPlus, Widgets};
 
 use anyhow::*;
-
-use ue_rec_deps_seeker::{find_rec_deps, CACHE_CONFIG_PATH};
+use tracing_subscriber::{fmt::format::FmtSpan, EnvFilter};
+
+use itertools::Itertools;
+use ue_rec_deps_seeker::{
+    cmp_paths,
+    file_info::FileType,
+    find_rec_deps,
+    options::AnalysisOptions,
+    project::{Project, BUILD_CS_SUFFIX, DEFAULT_CMAKE_LISTS},
+    report::{Findings, RecursivePaths, ReportFormat},
+    settings::Settings,
+    CACHE_CONFIG_PATH,
+};
 
 #[derive(Copy, Clone)]
 enum ArgPath {
@@ -43,7 +50,12 @@ impl ArgPath {
 enum AppMsg {
     Choose(ArgPath),
     Update((ArgPath, String)),
+    UpdateExcludedModules(String),
+    // Index into `AppModel::recent`
+    LoadRecent(usize),
     StartAlgo,
+    // Sent by the worker thread `StartAlgo` runs the analysis on
+    AlgoFinished(Result<Findings>),
 }
 
 #[tracker::track]
@@ -51,48 +63,95 @@ struct AppModel {
     project_path: Option<String>,
     entry_point: Option<String>,
     output_file: Option<String>,
+    // Comma separated
+    excluded_modules: String,
+    // Not editable in the window, kept from the settings so a run doesn't reset them
+    format: Option<ReportFormat>,
+    max_depth: Option<usize>,
+    keep_template_impl_cycles: bool,
+    // Why each path can't be used yet, in the `paths_arr` order
+    path_errors: [Option<String>; 3],
     was_successful: Option<bool>,
+    // Output file of the analysis in progress, `None` when idle
+    running_output_file: Option<String>,
+    recursive_paths: RecursivePaths,
+    recent: Vec<Settings>,
 }
 
 impl AppModel {
     fn new() -> Result<Self> {
-        let config_path = Path::new(CACHE_CONFIG_PATH);
-        let (project_path, entry_point, output_file) =
-            if config_path.exists() && config_path.is_file() {
-                let file = File::open(config_path)?;
-                let lines = BufReader::new(file).lines();
-
-                let mut peo = [None, None, None];
-                for (index, line) in lines.enumerate() {
-                    if index > 2 {
-                        break;
-                    }
+        let settings = Settings::load(CACHE_CONFIG_PATH)?;
+        info!("{:?}", settings);
+
+        let mut model = Self {
+            project_path: settings.project_path,
+            entry_point: settings.entry_point,
+            output_file: settings.output_file,
+            excluded_modules: settings.excluded_modules.join(", "),
+            format: settings.format,
+            max_depth: settings.max_depth,
+            keep_template_impl_cycles: settings.keep_template_impl_cycles,
+            path_errors: [None, None, None],
+            was_successful: None,
+            running_output_file: None,
+            recursive_paths: RecursivePaths::new(),
+            recent: settings.recent,
+            tracker: 0,
+        };
+        model.path_errors = model.validate_paths();
 
-                    if let std::result::Result::Ok(line) = line {
-                        let var_name = match index {
-                            0 => "project_path",
-                            1 => "entry_point",
-                            2 => "output_file",
-                            _ => "",
-                        };
-                        info!("{}: {}", var_name, line);
+        Ok(model)
+    }
 
-                        peo[index] = Some(line)
+    fn validate_paths(&self) -> [Option<String>; 3] {
+        let project_error = match &self.project_path {
+            None => Some("Not set".to_string()),
+            Some(project_path) if !Project::has_module_definitions(project_path) => Some(format!(
+                "No {} or *{} in the project",
+                DEFAULT_CMAKE_LISTS, BUILD_CS_SUFFIX
+            )),
+            Some(_) => None,
+        };
+
+        let entry_point_error = match &self.entry_point {
+            None => Some("Not set".to_string()),
+            Some(entry_point) if !Path::new(entry_point).is_file() => {
+                Some("File doesn't exist".to_string())
+            }
+            Some(entry_point)
+                if Path::new(entry_point)
+                    .extension()
+                    .and_then(|extension| extension.to_str())
+                    .and_then(FileType::from_extension)
+                    .is_none() =>
+            {
+                Some("File type is not supported".to_string())
+            }
+            Some(_) => None,
+        };
+
+        let output_file_error = match &self.output_file {
+            None => Some("Not set".to_string()),
+            Some(output_file) => {
+                let output_dir = match Path::new(output_file).parent() {
+                    Some(parent) if !parent.as_os_str().is_empty() => parent,
+                    _ => Path::new("."),
+                };
+
+                match fs::metadata(output_dir) {
+                    std::result::Result::Ok(metadata) if !metadata.is_dir() => {
+                        Some("Output directory is not a directory".to_string())
+                    }
+                    std::result::Result::Ok(metadata) if metadata.permissions().readonly() => {
+                        Some("Output directory is not writable".to_string())
                     }
+                    std::result::Result::Ok(_) => None,
+                    Err(_) => Some("Output directory doesn't exist".to_string()),
                 }
+            }
+        };
 
-                (peo[0].clone(), peo[1].clone(), peo[2].clone())
-            } else {
-                (None, None, None)
-            };
-
-        Ok(Self {
-            project_path,
-            entry_point,
-            output_file,
-            was_successful: None,
-            tracker: 0,
-        })
+        [project_error, entry_point_error, output_file_error]
     }
 
     fn all_paths(&self) -> (bool, Option<String>) {
@@ -132,6 +191,15 @@ impl AppModel {
         )
     }
 
+    fn excluded_modules_set(&self) -> HashSet<String> {
+        self.excluded_modules
+            .split(',')
+            .map(|module| module.trim())
+            .filter(|module| !module.is_empty())
+            .map(|module| module.to_string())
+            .collect()
+    }
+
     fn paths_arr(&self) -> [&Option<String>; 3] {
         [&self.project_path, &self.entry_point, &self.output_file]
     }
@@ -148,7 +216,7 @@ impl AppUpdate for AppModel {
         &mut self,
         msg: Self::Msg,
         _components: &Self::Components,
-        _sender: Sender<Self::Msg>,
+        sender: Sender<Self::Msg>,
     ) -> bool {
         self.reset();
 
@@ -223,7 +291,26 @@ impl AppUpdate for AppModel {
                 ArgPath::EntryPoint => self.set_entry_point(Some(path_str)),
                 ArgPath::OutputFile => self.set_output_file(Some(path_str)),
             },
+            AppMsg::UpdateExcludedModules(excluded_modules) => {
+                self.set_excluded_modules(excluded_modules)
+            }
+            AppMsg::LoadRecent(index) => {
+                if let Some(recent) = self.recent.get(index).cloned() {
+                    self.set_project_path(recent.project_path);
+                    self.set_entry_point(recent.entry_point);
+                    self.set_output_file(recent.output_file);
+                    self.set_excluded_modules(recent.excluded_modules.join(", "));
+                    self.set_format(recent.format);
+                    self.set_max_depth(recent.max_depth);
+                    self.set_keep_template_impl_cycles(recent.keep_template_impl_cycles);
+                }
+            }
             AppMsg::StartAlgo => {
+                if self.running_output_file.is_some() {
+                    warn!("The algorithm is already running");
+                    return true;
+                }
+
                 return match self.all_paths() {
                     (false, Some(message)) => {
                         error!("{}", message);
@@ -232,41 +319,77 @@ impl AppUpdate for AppModel {
                     (true, None) => {
                         let (project_path, entry_point, output_file_path) = self.unwrap_all();
 
-                        let success =
-                            match find_rec_deps(&project_path, &entry_point, &output_file_path) {
-                                std::result::Result::Ok(_) => true,
-                                Err(err) => {
-                                    error!("{}", err);
-                                    false
-                                }
-                            };
-
-                        self.set_was_successful(Some(success));
-
-                        if success {
-                            let open_file = MessageDialog::new()
-                                .set_type(MessageType::Info)
-                                .set_title("Success!")
-                                .set_text("Do you want to open the file?")
-                                .show_confirm()
-                                .unwrap();
-
-                            if open_file && open::that(&output_file_path).is_err() {
-                                error!("Couldn't open the file with the default text editor!");
-                                return false;
-                            }
-                        }
+                        let mut options =
+                            AnalysisOptions::new(&project_path, &entry_point, &output_file_path)
+                                .excluded_modules(self.excluded_modules_set())
+                                .max_depth(self.max_depth)
+                                .keep_template_impl_cycles(self.keep_template_impl_cycles);
+                        options.format = self.format;
+
+                        self.set_was_successful(None);
+                        self.set_running_output_file(Some(output_file_path));
 
-                        success
+                        // Keeps the main loop free, the window would freeze on big engines
+                        // otherwise
+                        thread::spawn(move || {
+                            send!(sender, AppMsg::AlgoFinished(find_rec_deps(options)))
+                        });
+
+                        true
                     }
                     _ => {
                         error!("Something went horribly wrong with getting info about paths");
                         false
                     }
+                };
+            }
+            AppMsg::AlgoFinished(result) => {
+                let output_file_path = match self.running_output_file.clone() {
+                    Some(output_file_path) => output_file_path,
+                    None => {
+                        error!("Got the algorithm's result without starting it");
+                        return true;
+                    }
+                };
+                self.set_running_output_file(None);
+
+                let success = match result {
+                    std::result::Result::Ok(findings) => {
+                        if let Findings::Cycles(cycle_report) = findings {
+                            self.set_recursive_paths(cycle_report.into_recursive_paths());
+                        }
+
+                        true
+                    }
+                    Err(err) => {
+                        error!("{}", err);
+                        false
+                    }
+                };
+
+                self.set_was_successful(Some(success));
+
+                if success {
+                    let open_file = MessageDialog::new()
+                        .set_type(MessageType::Info)
+                        .set_title("Success!")
+                        .set_text("Do you want to open the file?")
+                        .show_confirm()
+                        .unwrap();
+
+                    if open_file && open::that(&output_file_path).is_err() {
+                        error!("Couldn't open the file with the default text editor!");
+                        return false;
+                    }
                 }
+
+                return success;
             }
         }
 
+        let path_errors = self.validate_paths();
+        self.set_path_errors(path_errors);
+
         true
     }
 }
@@ -274,7 +397,12 @@ impl AppUpdate for AppModel {
 struct AppWidgets {
     window: gtk::ApplicationWindow,
     entries: [gtk::Entry; 3],
+    excluded_modules_entry: gtk::Entry,
+    entry_errors: [gtk::Label; 3],
+    start_algo_button: gtk::Button,
     success_message: gtk::Label,
+    // Cycle end file -> paths leading to it
+    cycles_store: gtk::TreeStore,
 }
 
 impl Widgets<AppModel, ()> for AppWidgets {
@@ -294,12 +422,50 @@ impl Widgets<AppModel, ()> for AppWidgets {
 
         window.set_child(Some(&main_container));
 
+        if !model.recent.is_empty() {
+            let recent_sender = sender.clone();
+
+            // The first item only says what the drop down is for, `LoadRecent` is offset by it
+            let recent_labels = std::iter::once("Recent projects...".to_string())
+                .chain(model.recent.iter().map(|recent| {
+                    format!(
+                        "{} ({})",
+                        recent.project_path.as_deref().unwrap_or_default(),
+                        recent
+                            .entry_point
+                            .as_deref()
+                            .and_then(|entry_point| Path::new(entry_point).file_name())
+                            .and_then(|file_name| file_name.to_str())
+                            .unwrap_or_default()
+                    )
+                }))
+                .collect::<Vec<_>>();
+
+            let recent_drop_down = gtk::DropDown::from_strings(
+                &recent_labels
+                    .iter()
+                    .map(|label| label.as_str())
+                    .collect::<Vec<_>>(),
+            );
+            recent_drop_down.connect_selected_notify(move |drop_down| {
+                if drop_down.selected() > 0 {
+                    send!(
+                        recent_sender,
+                        AppMsg::LoadRecent(drop_down.selected() as usize - 1)
+                    );
+                }
+            });
+
+            main_container.append(&recent_drop_down);
+        }
+
         let mut i = 0;
         let paths = model.paths_arr();
-        let entries =
+        let entries_and_errors =
             [ArgPath::Project, ArgPath::EntryPoint, ArgPath::OutputFile].map(|entry_type| {
                 let btn_sender = sender.clone();
                 let entry_sender = sender.clone();
+                let drop_sender = sender.clone();
 
                 let hbox = gtk::Box::builder()
                     .orientation(gtk::Orientation::Horizontal)
@@ -333,25 +499,105 @@ impl Widgets<AppModel, ()> for AppWidgets {
                 });
                 button.connect_clicked(move |_| send!(btn_sender, AppMsg::Choose(entry_type)));
 
+                // Files and folders dragged in from a file manager
+                let drop_target = gtk::DropTarget::new(gio::File::static_type(), DragAction::COPY);
+                drop_target.connect_drop(move |_, value, _, _| {
+                    let path = match value.get::<gio::File>() {
+                        std::result::Result::Ok(file) => file.path(),
+                        Err(_) => None,
+                    };
+
+                    match path.as_ref().and_then(|path| path.to_str()) {
+                        Some(path) => {
+                            send!(drop_sender, AppMsg::Update((entry_type, path.to_string())));
+                            true
+                        }
+                        None => {
+                            error!("Dropped item is not a local file");
+                            false
+                        }
+                    }
+                });
+                entry.add_controller(&drop_target);
+
+                let error_label = gtk::Label::new(model.path_errors[i].as_deref());
+                error_label.add_css_class("error");
+                error_label.set_visible(model.path_errors[i].is_some());
+
                 main_container.append(&hbox);
+                main_container.append(&error_label);
 
                 i += 1;
 
-                entry
+                (entry, error_label)
             });
+        let entries = entries_and_errors.clone().map(|(entry, _)| entry);
+        let entry_errors = entries_and_errors.map(|(_, error_label)| error_label);
+
+        let excluded_modules_sender = sender.clone();
+
+        let excluded_modules_hbox = gtk::Box::builder()
+            .orientation(gtk::Orientation::Horizontal)
+            .spacing(5)
+            .build();
+        excluded_modules_hbox.set_margin_all(5);
+
+        let excluded_modules_label = gtk::Label::new(Some("Excluded Modules"));
+        let excluded_modules_entry = gtk::Entry::builder()
+            .editable(true)
+            .placeholder_text("ex. Engine/Source/ThirdParty/zlib, Engine/Source/Developer/...")
+            .text(&model.excluded_modules)
+            .build();
+
+        excluded_modules_hbox.append(&excluded_modules_label);
+        excluded_modules_hbox.append(&excluded_modules_entry);
+
+        excluded_modules_entry.connect_changed(move |e| {
+            send!(
+                excluded_modules_sender,
+                AppMsg::UpdateExcludedModules(e.buffer().text())
+            )
+        });
 
-        let start_algo_button = gtk::Button::builder().label("Start Algorithm").build();
+        main_container.append(&excluded_modules_hbox);
+
+        let start_algo_button = gtk::Button::builder()
+            .label("Start Algorithm")
+            .sensitive(model.path_errors.iter().all(|error| error.is_none()))
+            .build();
         let success_message = gtk::Label::new(Some("Run Algo"));
 
+        let cycles_store = gtk::TreeStore::new(&[String::static_type()]);
+        let cycles_view = gtk::TreeView::builder()
+            .model(&cycles_store)
+            .headers_visible(false)
+            .build();
+
+        let cycles_column = gtk::TreeViewColumn::new();
+        let cycles_cell = gtk::CellRendererText::new();
+        cycles_column.pack_start(&cycles_cell, true);
+        cycles_column.add_attribute(&cycles_cell, "text", 0);
+        cycles_view.append_column(&cycles_column);
+
+        let cycles_window = gtk::ScrolledWindow::builder()
+            .vexpand(true)
+            .child(&cycles_view)
+            .build();
+
         main_container.append(&start_algo_button);
         main_container.append(&success_message);
+        main_container.append(&cycles_window);
 
         start_algo_button.connect_clicked(move |_| send!(sender, AppMsg::StartAlgo));
 
         Self {
             window,
             entries,
+            excluded_modules_entry,
+            entry_errors,
+            start_algo_button,
             success_message,
+            cycles_store,
         }
     }
 
@@ -375,13 +621,65 @@ impl Widgets<AppModel, ()> for AppWidgets {
             }
         }
 
-        if model.changed(AppModel::was_successful()) {
-            match model.was_successful {
-                Some(was_successful) => self.success_message.set_text(match was_successful {
-                    true => "Success!",
-                    false => "Failed!",
-                }),
-                None => self.success_message.set_text("Run Algo"),
+        // Only when it differs, setting the text while typing would move the cursor
+        if model.changed(AppModel::excluded_modules())
+            && self.excluded_modules_entry.text() != model.excluded_modules
+        {
+            self.excluded_modules_entry
+                .set_text(&model.excluded_modules);
+        }
+
+        if model.changed(AppModel::path_errors()) {
+            for (error_label, error) in self.entry_errors.iter().zip(model.path_errors.iter()) {
+                error_label.set_text(error.as_deref().unwrap_or_default());
+                error_label.set_visible(error.is_some());
+            }
+        }
+
+        if model.changed(AppModel::path_errors() | AppModel::running_output_file()) {
+            self.start_algo_button.set_sensitive(
+                model.running_output_file.is_none()
+                    && model.path_errors.iter().all(|error| error.is_none()),
+            );
+        }
+
+        if model.changed(AppModel::was_successful() | AppModel::running_output_file()) {
+            match (model.was_successful, &model.running_output_file) {
+                (_, Some(_)) => self.success_message.set_text("Running..."),
+                (Some(was_successful), None) => {
+                    self.success_message.set_text(match was_successful {
+                        true => "Success!",
+                        false => "Failed!",
+                    })
+                }
+                (None, None) => self.success_message.set_text("Run Algo"),
+            }
+        }
+
+        if model.changed(AppModel::recursive_paths()) {
+            self.cycles_store.clear();
+
+            for (file_name, paths) in model
+                .recursive_paths
+                .iter()
+                .sorted_by_key(|(file_name, _)| *file_name)
+            {
+                let file_row = self.cycles_store.insert_with_values(
+                    None,
+                    None,
+                    &[(0, &format!("{} ({})", file_name, paths.len()))],
+                );
+
+                for path in paths
+                    .iter()
+                    .sorted_by(|path1, path2| cmp_paths(path1, path2))
+                {
+                    self.cycles_store.insert_with_values(
+                        Some(&file_row),
+                        None,
+                        &[(0, &path.join("->"))],
+                    );
+                }
             }
         }
     }
@@ -389,7 +687,10 @@ impl Widgets<AppModel, ()> for AppWidgets {
 
 fn main() -> Result<()> {
     std::env::set_var("RUST_LOG", "trace");
-    pretty_env_logger::init_timed();
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .with_span_events(FmtSpan::CLOSE)
+        .init();
 
     let model = AppModel::new()?;
 
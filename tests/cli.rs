@@ -0,0 +1,100 @@
+use std::{
+    fs,
+    path::Path,
+    process::{Command, Output},
+};
+
+use tempfile::TempDir;
+
+// `Core` with `A.h` and `B.h` including each other, and a `Clean.h` outside of the cycle
+fn fixture_project() -> TempDir {
+    let project_dir = TempDir::new().unwrap();
+    let public_dir = project_dir.path().join("Engine/Source/Runtime/Core/Public");
+    fs::create_dir_all(&public_dir).unwrap();
+
+    fs::write(public_dir.join("A.h"), "#pragma once\n#include \"B.h\"\n").unwrap();
+    fs::write(public_dir.join("B.h"), "#pragma once\n#include \"A.h\"\n").unwrap();
+    fs::write(public_dir.join("Clean.h"), "#pragma once\n").unwrap();
+
+    fs::write(
+        project_dir.path().join("cmake-includes.cmake"),
+        format!(
+            "set(INCLUDE_DIRECTORIES\n\t\"{}\"\n)\n",
+            public_dir.display()
+        ),
+    )
+    .unwrap();
+    fs::write(
+        project_dir.path().join("CMakeLists.txt"),
+        "include(\"${CMAKE_CURRENT_SOURCE_DIR}/cmake-includes.cmake\")\n",
+    )
+    .unwrap();
+
+    project_dir
+}
+
+fn analyze(project_dir: &Path, entry: &str, output: &str, extra_args: &[&str]) -> Output {
+    let entry = project_dir
+        .join("Engine/Source/Runtime/Core/Public")
+        .join(entry);
+
+    Command::new(env!("CARGO_BIN_EXE_cli"))
+        // Keeps the settings file the run writes out of the repository
+        .current_dir(project_dir)
+        .args(["analyze", "--project"])
+        .arg(project_dir)
+        .arg("--entry")
+        .arg(entry)
+        .arg("--output")
+        .arg(project_dir.join(output))
+        .args(extra_args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn exits_with_status_2_when_cycles_are_found() {
+    let project_dir = fixture_project();
+
+    let output = analyze(project_dir.path(), "A.h", "report.txt", &[]);
+
+    assert_eq!(output.status.code(), Some(2));
+    let report = fs::read_to_string(project_dir.path().join("report.txt")).unwrap();
+    assert!(report.contains("A.h<->B.h"));
+}
+
+#[test]
+fn exits_with_status_0_without_cycles() {
+    let project_dir = fixture_project();
+
+    let output = analyze(project_dir.path(), "Clean.h", "report.txt", &[]);
+
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn format_overrides_the_output_extension() {
+    let project_dir = fixture_project();
+
+    analyze(
+        project_dir.path(),
+        "A.h",
+        "report.txt",
+        &["--format", "json"],
+    );
+
+    let report = fs::read_to_string(project_dir.path().join("report.txt")).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&report).unwrap();
+    assert!(json.get("cycles").is_some());
+}
+
+#[test]
+fn unwritable_settings_dont_fail_the_run() {
+    let project_dir = fixture_project();
+    // Writing the settings file fails when a directory is in its place
+    fs::create_dir(project_dir.path().join(".cache")).unwrap();
+
+    let output = analyze(project_dir.path(), "Clean.h", "report.txt", &[]);
+
+    assert_eq!(output.status.code(), Some(0));
+}